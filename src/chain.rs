@@ -6,25 +6,106 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::components::AccountId;
+use crate::components::AccountNonce;
+use crate::components::AddressPrefix;
+use crate::components::ChainId;
 use crate::components::Balance;
 use crate::components::block::Block;
 use crate::components::block::BlockCollection;
+use crate::components::block_tree::BlockTree;
+use crate::components::error::BlockVerificationError;
+use crate::components::error::ChainValidationError;
+use crate::components::error::ConsensusError;
+use crate::components::miner::Miner;
+use crate::components::consensus::ConsensusResult;
+use crate::components::error::DispatchError;
 use crate::components::hash::Hash;
+use crate::components::hasher::HashAlgorithm;
+use crate::components::multisig::MultisigInfo;
+use crate::components::multisig::MultisigProposal;
+use crate::components::multisig::MultisigStorage;
+use crate::components::multisig::MultisigThreshold;
+use crate::components::origin::Origin;
+use crate::components::signature::Pair;
+use crate::components::signature::PublicKey;
+use crate::components::staking::StakingStorage;
+use crate::components::staking::UnlockChunk;
+use crate::components::BlockNumber;
 use crate::components::consensus::Consensus;
-use crate::components::consensus::ProofOfWork;
+use crate::components::consensus::ConsensusEngine;
+use crate::components::difficulty::Target;
+use crate::components::difficulty::Work;
 use crate::components::transaction::Operation;
 use crate::components::transaction::Transaction;
 use crate::components::transaction::TransactionResult;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChainProperties {
+    pub chain_id: ChainId,
+    pub address_prefix: AddressPrefix,
     pub exestinsial_deposit: Balance,
-    pub difficulty: usize,
+    pub target: Target,
     pub time_tolorant: Duration,
-    pub consensus: ProofOfWork,
+    pub consensus: ConsensusEngine,
+    pub sudo: Option<AccountId>,
+    #[serde(default)]
+    pub authorities: Vec<PublicKey>,
+    pub slot_duration: Duration,
+    #[serde(default)]
+    pub unbonding_period: BlockNumber,
+    // Zero block time keeps PoW target fixed
+    pub target_block_time: Duration,
+    pub retarget_half_life: Duration,
+    // How far ahead of local clock a block timestamp may be
+    pub max_future_drift: Duration,
+    #[serde(default)]
+    pub hasher: HashAlgorithm,
+}
+
+/// Everything genesis block and state are made from. Nodes given same
+/// config derive same genesis hash
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    pub properties: ChainProperties,
+    pub system_allowance: Balance,
+    // Bonded from system allowance in this order
+    pub stakers: Vec<(AccountId, Balance)>,
+    pub timestamp: u64,
+}
+
+impl GenesisConfig {
+    /// Hash under the config's own hasher. Accounts are written under its own
+    /// prefix too, so hash does not depend on whichever chain is running
+    pub fn get_hash(&self) -> Hash {
+        let properties = &self.properties;
+        let address = |account: &AccountId| account.to_address_under(properties.address_prefix, properties.hasher);
+        let mut config_as_json = serde_json::to_value(self)
+            .expect("Cannot represent GenesisConfig{} as json value");
+        config_as_json["properties"]["sudo"] = properties.sudo.as_ref().map(address).into();
+        config_as_json["stakers"] = self.stakers
+            .iter()
+            .map(|(staker, stake)| serde_json::json!([address(staker), stake]))
+            .collect();
+
+        Hash::new_under(properties.hasher, config_as_json.to_string())
+    }
 }
 
 pub type MappedAccountInfo = HashMap<AccountId, AccountInfo>;
+/// Kept apart from accounts so a signer without balance gets a nonce
+/// but does not become an account below existential deposit
+pub type MappedNonces = HashMap<AccountId, AccountNonce>;
+
+/// Part of chain mutated by transactions. Taken before executing a block
+/// so the block can be rolled back as a whole
+#[derive(Clone)]
+pub struct StateSnapshot {
+    accounts: MappedAccountInfo,
+    nonces: MappedNonces,
+    multisig: MultisigStorage,
+    staking: StakingStorage,
+    sudo: Option<AccountId>,
+}
 
 #[derive(PartialEq, Eq, Deserialize, Serialize, Clone)]
 pub struct AccountInfo {
@@ -42,10 +123,34 @@ impl Default for AccountInfo {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Chain {
     pub chain_info: Cow<'static, str>,
+    genesis_hash: Hash,
     pub blocks: BlockCollection,
     pub accounts: MappedAccountInfo,
+    pub nonces: MappedNonces,
     pub properties: ChainProperties,
+    pub multisig: MultisigStorage,
+    pub staking: StakingStorage,
     system_account: AccountId,
+    // Genesis state is rebuilt from it whenever chain is replayed
+    genesis_config: GenesisConfig,
+    // Height from which each `set_difficulty` target applies while retargeting
+    // is off, so replay can check blocks against the target of their time
+    #[serde(default)]
+    fixed_target_changes: Vec<(BlockNumber, Target)>,
+    // Key this node seals blocks with under ProofOfAuthority. Never leaves the node
+    #[serde(skip)]
+    pub author_key: Option<Pair>,
+    #[serde(skip)]
+    pub miner: Miner,
+    // Every known block including forks. `blocks` is the canonical path through it
+    #[serde(skip)]
+    pub block_tree: BlockTree,
+    // State before each canonical block after genesis, used to revert on reorg
+    #[serde(skip)]
+    state_history: Vec<StateSnapshot>,
+    // Transactions waiting for background miner to put them in a block
+    #[serde(skip)]
+    pub pending_transactions: Vec<Transaction>,
 }
 
 impl core::fmt::Display for Chain {
@@ -57,32 +162,91 @@ impl core::fmt::Display for Chain {
 }
 
 impl Chain {
-    pub fn new(chain_info: Cow<'static, str>,
-               properties: ChainProperties,
-               system_allowance: Balance,
-               ) -> Self
-    {
-        let system_account = Hash::raw([u8::MAX; crate::components::SU_HASHER_LEN]);
+    pub fn new(chain_info: Cow<'static, str>, genesis_config: GenesisConfig) -> Result<Self, DispatchError> {
+        // Prefix and hasher of the running chain are only switched once
+        // genesis state is built, so a bad config leaves them as they were
+        let chain = Self::at_genesis(chain_info, genesis_config)?;
+
+        // Accounts of this chain are displayed, parsed and hashed with its own prefix and hasher
+        crate::components::account::set_address_prefix(chain.properties.address_prefix);
+        crate::components::hasher::set_hash_algorithm(chain.properties.hasher);
+
+        Ok(chain)
+    }
+
+    // Chain of genesis block alone, with state made from config and nothing else
+    fn at_genesis(chain_info: Cow<'static, str>, genesis_config: GenesisConfig) -> Result<Self, DispatchError> {
+        let system_account = AccountId::raw([u8::MAX; crate::components::SU_HASHER_LEN]);
         let system_account_info = AccountInfo {
-            balance: system_allowance,
+            balance: genesis_config.system_allowance,
         };
 
         let predefined_accounts = vec![(system_account.clone(), system_account_info)];
-        Chain {
+        let mut chain = Chain {
             chain_info,
-            blocks: vec![Block::get_genesis()],
+            genesis_hash: Hash::raw([0; crate::components::SU_HASHER_LEN]),
+            blocks: vec![],
             accounts: predefined_accounts.into_iter().collect(),
-            properties,
+            nonces: Default::default(),
+            properties: genesis_config.properties.clone(),
+            multisig: Default::default(),
+            staking: Default::default(),
             system_account,
+            genesis_config: genesis_config.clone(),
+            fixed_target_changes: vec![],
+            author_key: None,
+            miner: Miner::default(),
+            block_tree: BlockTree::default(),
+            state_history: vec![],
+            pending_transactions: vec![],
+        };
+
+        for (staker, stake) in &genesis_config.stakers {
+            chain.genesis_bond(staker.clone(), *stake)?;
         }
+
+        let genesis = Block::get_genesis(&genesis_config);
+        chain.genesis_hash = genesis.get_hash(genesis_config.properties.hasher);
+        chain.block_tree.insert(chain.genesis_hash.clone(), genesis.clone(), genesis.work());
+        chain.blocks.push(genesis);
+
+        Ok(chain)
     }
 
     pub fn execute(&mut self, transaction: Transaction) -> TransactionResult {
-        if !transaction.operation.is_privilaged(&transaction.initiator) {
-            Err("Insufficient permission")?;
+        transaction.verify_signature()?;
+
+        // Root is never claimed by transaction. Only Sudo dispatch grants it
+        if transaction.initiator.root().is_some() {
+            Err(DispatchError::UnauthenticatedRoot)?;
+        }
+
+        // Only signature makes nonce and chain id binding
+        // so they are checked for signed origin alone
+        let signer = transaction.initiator.signed().cloned();
+        if let Some(signer) = &signer {
+            self.check_chain_id(transaction.chain_id)?;
+            self.check_nonce(signer, transaction.nonce)?;
+        }
+
+        self.dispatch(transaction.operation, &transaction.initiator)?;
+
+        if let Some(signer) = signer {
+            self.increment_nonce(&signer);
         }
 
-        match transaction.operation {
+        Ok(())
+    }
+
+    /// Run operation on behalf of origin. Origin is assumed to be authenticated
+    pub fn dispatch(&mut self, operation: Operation, origin: &Origin) -> TransactionResult {
+        if !operation.is_privilaged(origin) {
+            Err(DispatchError::BadOrigin)?;
+        }
+
+        // is_privilaged() already made sure these operation have signed origin
+        let signed_origin = || origin.signed().cloned().ok_or(DispatchError::BadOrigin);
+        match operation {
             Operation::Empty => self.empty_operation(),
             Operation::Panic => self.panic_operation(),
             Operation::DestroyAccount { account_id } =>
@@ -91,57 +255,182 @@ impl Chain {
                 self.transfer_fund(sender, receiver, amount, true),
             Operation::Airdrop { receiver, amount } =>
                 self.airdrop(receiver, amount),
+            Operation::CreateMultisig { signatories, threshold } =>
+                self.create_multisig(signatories, threshold),
+            Operation::ProposeMultisig { multisig, call } =>
+                self.propose_multisig(signed_origin()?, multisig, *call),
+            Operation::ApproveMultisig { multisig, call_hash } =>
+                self.approve_multisig(signed_origin()?, multisig, call_hash),
+            Operation::CancelMultisig { multisig, call_hash } =>
+                self.cancel_multisig(signed_origin()?, multisig, call_hash),
+            Operation::Sudo { call } =>
+                self.sudo(signed_origin()?, *call),
+            Operation::SetSudoKey { new_key } =>
+                self.set_sudo_key(signed_origin()?, new_key),
+            Operation::RemoveSudo =>
+                self.remove_sudo(signed_origin()?),
+            Operation::Bond { amount } =>
+                self.bond(signed_origin()?, amount),
+            Operation::Unbond { amount } =>
+                self.unbond(signed_origin()?, amount),
+            Operation::WithdrawUnbonded =>
+                self.withdraw_unbonded(signed_origin()?),
+        }
+    }
+
+    pub fn snapshot_state(&self) -> StateSnapshot {
+        StateSnapshot {
+            accounts: self.accounts.clone(),
+            nonces: self.nonces.clone(),
+            multisig: self.multisig.clone(),
+            staking: self.staking.clone(),
+            sudo: self.properties.sudo.clone(),
         }
     }
+
+    pub fn restore_state(&mut self, snapshot: StateSnapshot) {
+        self.accounts = snapshot.accounts;
+        self.nonces = snapshot.nonces;
+        self.multisig = snapshot.multisig;
+        self.staking = snapshot.staking;
+        self.properties.sudo = snapshot.sudo;
+    }
 }
 
 impl Chain {
+    /// Identifies the chain. Peers with different genesis hash are on different chains
+    pub fn genesis_hash(&self) -> &Hash {
+        &self.genesis_hash
+    }
+
+    pub fn get_genesis_block(&self) -> &Block {
+        self.blocks
+            .first()
+            .expect("There will always be at least one bloock in chain")
+    }
+
+    /// Sum of work of every block. Heavier chain is the better one
+    pub fn cumulative_work(&self) -> Work {
+        self.blocks
+            .iter()
+            .fold(Work::zero(), |total_work, block| total_work.saturating_add(block.work()))
+    }
+
     pub fn get_latest_block(&self) -> &Block {
         self.blocks
             .last()
             .expect("There will always be at least one bloock in chain")
     }
 
-    pub fn transfer_fund<'chain>(
-        &'chain mut self,
+    // Transactions are executed before their block is appended
+    fn executing_height(&self) -> BlockNumber {
+        self.get_latest_block().header.height + 1
+    }
+
+    pub fn account_nonce(&self, account: &AccountId) -> AccountNonce {
+        self.nonces
+            .get(account)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Nonce for next transaction of `account`, counting ones still pending
+    pub fn next_account_nonce(&self, account: &AccountId) -> AccountNonce {
+        let pending_count = self.pending_transactions
+            .iter()
+            .filter(|transaction| transaction.initiator.signed() == Some(account))
+            .count();
+        self.account_nonce(account) + pending_count as AccountNonce
+    }
+
+    fn check_chain_id(&self, chain_id: ChainId) -> TransactionResult {
+        let expected_chain_id = self.properties.chain_id;
+        if chain_id != expected_chain_id {
+            Err(DispatchError::WrongChainId {
+                expected: expected_chain_id,
+                found: chain_id,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn check_nonce(&self, account: &AccountId, nonce: AccountNonce) -> TransactionResult {
+        let expected_nonce = self.account_nonce(account);
+        if nonce < expected_nonce {
+            Err(DispatchError::StaleNonce {
+                account: account.clone(),
+                expected: expected_nonce,
+                found: nonce,
+            })?;
+        }
+        if nonce > expected_nonce {
+            Err(DispatchError::FutureNonce {
+                account: account.clone(),
+                expected: expected_nonce,
+                found: nonce,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    // Nonce is stored even for signer without balance. Otherwise it stays 0
+    // and the same signed transaction could be replayed again and again
+    fn increment_nonce(&mut self, account: &AccountId) {
+        *self.nonces
+            .entry(account.clone())
+            .or_default() += 1;
+    }
+
+    pub fn transfer_fund(
+        &mut self,
         sender: AccountId,
         receiver: AccountId,
         amount: Balance,
         keep_alive: bool,
     ) -> TransactionResult {
         let sender_balance = self.accounts.get(&sender)
-            .ok_or("sender account doesn't exists")?
+            .ok_or_else(|| DispatchError::AccountNotFound(sender.clone()))?
             .balance;
 
         if sender_balance < amount {
-            Err("sender balance too low")?;
+            Err(DispatchError::InsufficientBalance {
+                account: sender.clone(),
+                balance: sender_balance,
+                amount,
+            })?;
         }
 
-        let sender_usable_balance = sender_balance - self.properties.exestinsial_deposit;
+        let sender_usable_balance = sender_balance.saturating_sub(self.properties.exestinsial_deposit);
         if sender_usable_balance < amount && keep_alive {
-            Err("can't kill sender")?;
+            Err(DispatchError::WouldKillAccount(sender.clone()))?;
         }
 
         self.accounts
             .entry(receiver.clone())
-            .or_insert_with(Default::default);
+            .or_default();
         
-        let mut receiver_increment_res: TransactionResult = Err("receiver amount never incremented".into());
+        let mut receiver_increment_res: TransactionResult = Err(DispatchError::AccountNotFound(receiver.clone()));
+        let receiver_account = receiver.clone();
         self.accounts
             .entry(receiver)
             .and_modify(|receiver_info| {
                 let receiver_balance = receiver_info.balance;
                 if receiver_balance + amount < self.properties.exestinsial_deposit {
-                    receiver_increment_res = Err("amount too low to createe receiver account".into());
+                    receiver_increment_res = Err(DispatchError::BelowExistentialDeposit {
+                        account: receiver_account,
+                        amount,
+                    });
                 } else {
-                    (*receiver_info).balance += amount;
+                    receiver_info.balance += amount;
                     receiver_increment_res = Ok(());
                 }
             });
         receiver_increment_res?;
 
         self.accounts.entry(sender).and_modify(|sender_info|{
-            (*sender_info).balance -= amount;
+            sender_info.balance -= amount;
         });
 
         Ok(())
@@ -151,9 +440,9 @@ impl Chain {
         let system_account = self.system_account.clone();
         self.transfer_fund(system_account, receiver, amount, true)
             .map_err(|err| {
-                match err.as_ref() {
-                    "sender balance too low"
-                        | "can't kill sender" => "system allowence too low".into(),
+                match err {
+                    DispatchError::InsufficientBalance { .. }
+                        | DispatchError::WouldKillAccount(_) => DispatchError::SystemAllowanceTooLow,
                     _ => err
                 }
             })
@@ -163,12 +452,248 @@ impl Chain {
         let system_account = self.system_account.clone();
         let account_balance = self.accounts
             .get(&account)
-            .ok_or("account does not exists to destroy")?
+            .ok_or_else(|| DispatchError::AccountNotFound(account.clone()))?
             .balance;
 
         self.transfer_fund(account, system_account, account_balance, false)
     }
 
+    pub fn create_multisig(&mut self, signatories: Vec<AccountId>, threshold: MultisigThreshold) -> TransactionResult {
+        let multisig_info = MultisigInfo::new(signatories, threshold);
+        if !multisig_info.is_valid() {
+            Err(DispatchError::InvalidMultisigThreshold {
+                threshold,
+                signatories: multisig_info.signatories.len(),
+            })?;
+        }
+
+        let multisig = multisig_info.account_id();
+        if self.multisig.accounts.contains_key(&multisig) {
+            Err(DispatchError::MultisigExists(multisig.clone()))?;
+        }
+        self.multisig.accounts.insert(multisig, multisig_info);
+
+        Ok(())
+    }
+
+    fn propose_multisig(&mut self, proposer: AccountId, multisig: AccountId, call: Operation) -> TransactionResult {
+        self.ensure_signatory(&proposer, &multisig)?;
+
+        let call_hash = crate::components::multisig::call_hash(&call);
+        let multisig_proposals = self.multisig.proposals
+            .entry(multisig.clone())
+            .or_default();
+        if multisig_proposals.contains_key(&call_hash) {
+            return Err(DispatchError::ProposalExists { multisig, call_hash });
+        }
+
+        // Proposing counts as first approval
+        let proposal = MultisigProposal {
+            proposer: proposer.clone(),
+            call,
+            approvals: vec![proposer],
+        };
+        multisig_proposals.insert(call_hash.clone(), proposal);
+
+        self.try_execute_multisig(multisig, call_hash)
+    }
+
+    fn approve_multisig(&mut self, approver: AccountId, multisig: AccountId, call_hash: Hash) -> TransactionResult {
+        self.ensure_signatory(&approver, &multisig)?;
+
+        let proposal = self.multisig.proposals
+            .get_mut(&multisig)
+            .and_then(|multisig_proposals| multisig_proposals.get_mut(&call_hash))
+            .ok_or_else(|| DispatchError::ProposalNotFound {
+                multisig: multisig.clone(),
+                call_hash: call_hash.clone(),
+            })?;
+        if proposal.approvals.contains(&approver) {
+            return Err(DispatchError::AlreadyApproved {
+                account: approver,
+                call_hash,
+            });
+        }
+        proposal.approvals.push(approver);
+
+        self.try_execute_multisig(multisig, call_hash)
+    }
+
+    fn cancel_multisig(&mut self, canceller: AccountId, multisig: AccountId, call_hash: Hash) -> TransactionResult {
+        let multisig_proposals = self.multisig.proposals
+            .get_mut(&multisig)
+            .filter(|multisig_proposals| multisig_proposals.contains_key(&call_hash))
+            .ok_or_else(|| DispatchError::ProposalNotFound {
+                multisig: multisig.clone(),
+                call_hash: call_hash.clone(),
+            })?;
+        if multisig_proposals[&call_hash].proposer != canceller {
+            return Err(DispatchError::NotProposer {
+                account: canceller,
+                call_hash,
+            });
+        }
+        multisig_proposals.remove(&call_hash);
+
+        Ok(())
+    }
+
+    // Dispatch the proposed call as multisig account once enough signatories approved it
+    fn try_execute_multisig(&mut self, multisig: AccountId, call_hash: Hash) -> TransactionResult {
+        let threshold = self.multisig.accounts[&multisig].threshold;
+        let multisig_proposals = self.multisig.proposals
+            .get_mut(&multisig)
+            .expect("Proposal is inserted before trying to execute");
+        if multisig_proposals[&call_hash].approvals.len() < usize::from(threshold) {
+            return Ok(());
+        }
+
+        let proposal = multisig_proposals
+            .remove(&call_hash)
+            .expect("Checked to exists above");
+        if multisig_proposals.is_empty() {
+            self.multisig.proposals.remove(&multisig);
+        }
+
+        self.dispatch(proposal.call, &Origin::Signed(multisig))
+    }
+
+    fn ensure_signatory(&self, account: &AccountId, multisig: &AccountId) -> TransactionResult {
+        let multisig_info = self.multisig.accounts
+            .get(multisig)
+            .ok_or_else(|| DispatchError::MultisigNotFound(multisig.clone()))?;
+        if !multisig_info.signatories.contains(account) {
+            Err(DispatchError::NotSignatory {
+                account: account.clone(),
+                multisig: multisig.clone(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn ensure_sudo(&self, account: &AccountId) -> TransactionResult {
+        match &self.properties.sudo {
+            None => Err(DispatchError::NoSudoKey),
+            Some(sudo_key) if sudo_key != account => Err(DispatchError::NotSudo(account.clone())),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn sudo(&mut self, caller: AccountId, call: Operation) -> TransactionResult {
+        self.ensure_sudo(&caller)?;
+        self.dispatch(call, &Origin::Root)
+    }
+
+    fn set_sudo_key(&mut self, caller: AccountId, new_key: AccountId) -> TransactionResult {
+        self.ensure_sudo(&caller)?;
+        self.properties.sudo = Some(new_key);
+
+        Ok(())
+    }
+
+    // One way. Once removed nothing can dispatch as Root again
+    fn remove_sudo(&mut self, caller: AccountId) -> TransactionResult {
+        self.ensure_sudo(&caller)?;
+        self.properties.sudo = None;
+
+        Ok(())
+    }
+
+    // Bonded stake leaves free balance but must not take account below existential deposit
+    fn bond(&mut self, stash: AccountId, amount: Balance) -> TransactionResult {
+        let exestinsial_deposit = self.properties.exestinsial_deposit;
+        let stash_info = self.accounts
+            .get_mut(&stash)
+            .ok_or_else(|| DispatchError::AccountNotFound(stash.clone()))?;
+
+        if stash_info.balance < amount {
+            return Err(DispatchError::InsufficientBalance {
+                account: stash,
+                balance: stash_info.balance,
+                amount,
+            });
+        }
+        if stash_info.balance - amount < exestinsial_deposit {
+            return Err(DispatchError::WouldKillAccount(stash));
+        }
+
+        stash_info.balance -= amount;
+        self.staking.ledgers
+            .entry(stash)
+            .or_default()
+            .bonded += amount;
+
+        Ok(())
+    }
+
+    fn unbond(&mut self, stash: AccountId, amount: Balance) -> TransactionResult {
+        let unlock_at = self.executing_height() + self.properties.unbonding_period;
+        let ledger = self.staking.ledgers
+            .get_mut(&stash)
+            .filter(|ledger| ledger.bonded >= amount);
+        let Some(ledger) = ledger else {
+            let bonded = self.staking.ledgers
+                .get(&stash)
+                .map(|ledger| ledger.bonded)
+                .unwrap_or(0);
+            return Err(DispatchError::InsufficientBonded {
+                account: stash,
+                bonded,
+                amount,
+            });
+        };
+
+        ledger.bonded -= amount;
+        ledger.unlocking.push(UnlockChunk {
+            amount,
+            unlock_at,
+        });
+
+        Ok(())
+    }
+
+    fn withdraw_unbonded(&mut self, stash: AccountId) -> TransactionResult {
+        let height = self.executing_height();
+        let ledger = self.staking.ledgers
+            .get_mut(&stash)
+            .ok_or_else(|| DispatchError::NothingToWithdraw(stash.clone()))?;
+
+        let amount = ledger.take_unlocked(height);
+        if amount == 0 {
+            return Err(DispatchError::NothingToWithdraw(stash));
+        }
+        if ledger.is_empty() {
+            self.staking.ledgers.remove(&stash);
+        }
+
+        self.accounts
+            .entry(stash)
+            .or_default()
+            .balance += amount;
+
+        Ok(())
+    }
+
+    /// Bond stake out of system allowance before any block is produced
+    /// so ProofOfStake chain has someone to lead its first slots
+    fn genesis_bond(&mut self, stash: AccountId, amount: Balance) -> TransactionResult {
+        let system_info = self.accounts
+            .get_mut(&self.system_account)
+            .ok_or(DispatchError::SystemAllowanceTooLow)?;
+        if system_info.balance < amount {
+            Err(DispatchError::SystemAllowanceTooLow)?;
+        }
+
+        system_info.balance -= amount;
+        self.staking.ledgers
+            .entry(stash)
+            .or_default()
+            .bonded += amount;
+
+        Ok(())
+    }
+
     pub fn empty_operation(&mut self) -> TransactionResult {
         Ok(())
     }
@@ -177,7 +702,454 @@ impl Chain {
         panic!("A panic becaused operation demands..")
     }
 
-    pub fn add_block(&mut self, new_block: Block) -> TransactionResult {
-        <ProofOfWork as Consensus>::add_new_block(self, new_block)
+    /// Accept block on top of any known block. Branch with most cumulative
+    /// work is canonical. PoA and PoS blocks weigh one each so longest wins
+    pub fn add_block(&mut self, new_block: Block) -> ConsensusResult {
+        self.restore_history()?;
+        let hasher = self.properties.hasher;
+        let block_hash = new_block.get_hash(hasher);
+        if self.block_tree.contains(&block_hash) {
+            return Err(ConsensusError::DuplicateBlock(block_hash));
+        }
+
+        let parent_hash = new_block.header.parent_block.clone();
+        if parent_hash == self.get_latest_block().get_hash(hasher) {
+            return <ConsensusEngine as Consensus>::add_new_block(self, new_block);
+        }
+
+        // Competing block is only executed once its branch takes over
+        let parent_entry = self.block_tree
+            .get(&parent_hash)
+            .ok_or(ConsensusError::UnknownParent(parent_hash))?;
+        <ConsensusEngine as Consensus>::verify_new_block(self, &parent_entry.block, &new_block)?;
+        let cumulative_work = parent_entry.cumulative_work.saturating_add(new_block.work());
+        self.block_tree.insert(block_hash.clone(), new_block, cumulative_work);
+
+        if cumulative_work > self.cumulative_work() {
+            self.reorganize(&block_hash)?;
+        }
+
+        Ok(())
+    }
+
+    /// Execute verified block on top of the tip. State is rolled back if any transaction fails
+    pub fn apply_block(&mut self, new_block: Block) -> ConsensusResult {
+        let old_state = self.snapshot_state();
+
+        for (tx_index, transaction) in new_block.transactions.iter().enumerate() {
+            let transaction_result = self.execute(transaction.clone());
+
+            if let Err(tx_err) = transaction_result {
+                self.restore_state(old_state);
+                return Err(ConsensusError::Transaction {
+                    tx_index,
+                    error: tx_err,
+                });
+            }
+        }
+
+        let cumulative_work = self.cumulative_work().saturating_add(new_block.work());
+        self.block_tree.insert(new_block.get_hash(self.properties.hasher), new_block.clone(), cumulative_work);
+        self.state_history.push(old_state);
+        self.blocks.push(new_block);
+        // Any seal being searched on the old tip is now useless
+        self.miner.cancel();
+
+        Ok(())
+    }
+
+    /// Replay every canonical block on top of genesis state with every check
+    /// except freshness, so chain can be verified long after it was made
+    pub fn validate_from_genesis(&self) -> Result<(), ChainValidationError> {
+        let genesis_height = self.get_genesis_block().header.height;
+        let mut replica = self.replica_at_genesis().map_err(|error| ChainValidationError {
+            height: genesis_height,
+            block: self.genesis_hash.clone(),
+            error: Box::new(error),
+        })?;
+
+        // Both the recorded genesis block and the one config gives must be the chain's genesis
+        let hasher = self.properties.hasher;
+        let genesis_hashes = [self.get_genesis_block().get_hash(hasher), replica.genesis_hash.clone()];
+        if let Some(found_genesis_hash) = genesis_hashes.into_iter().find(|found| found != &self.genesis_hash) {
+            return Err(ChainValidationError {
+                height: genesis_height,
+                block: found_genesis_hash.clone(),
+                error: Box::new(ConsensusError::Verification(BlockVerificationError::GenesisMismatch {
+                    expected: self.genesis_hash.clone(),
+                    found: found_genesis_hash,
+                })),
+            });
+        }
+
+        for block in self.blocks.iter().skip(1) {
+            let replay_res = <ConsensusEngine as Consensus>::verify_block(&replica, replica.get_latest_block(), block)
+                .map_err(ConsensusError::from)
+                .and_then(|()| replica.apply_block(block.clone()));
+            if let Err(error) = replay_res {
+                return Err(ChainValidationError {
+                    height: block.header.height,
+                    block: block.get_hash(hasher),
+                    error: Box::new(error),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Chain at genesis built from config alone. Own miner so replay does
+    // not cancel seal search of this node
+    fn replica_at_genesis(&self) -> Result<Chain, ConsensusError> {
+        let mut replica = Chain::at_genesis(self.chain_info.clone(), self.genesis_config.clone())
+            .map_err(ConsensusError::Genesis)?;
+        replica.fixed_target_changes = self.fixed_target_changes.clone();
+        replica.miner = Miner::new(1);
+
+        Ok(replica)
+    }
+
+    // State history and block tree are not serialized. Chain that went
+    // through serde gets them back by executing its canonical blocks again
+    fn restore_history(&mut self) -> ConsensusResult {
+        if self.state_history.len() + 1 == self.blocks.len() {
+            return Ok(());
+        }
+
+        let mut replica = self.replica_at_genesis()?;
+        for block in self.blocks.iter().skip(1) {
+            replica.apply_block(block.clone())?;
+        }
+        self.state_history = replica.state_history;
+        self.block_tree = replica.block_tree;
+
+        Ok(())
+    }
+
+    // Revert state to common ancestor of canonical chain and `new_tip` then
+    // replay the new branch. Canonical chain is left as it was if replay fails
+    fn reorganize(&mut self, new_tip: &Hash) -> ConsensusResult {
+        let canonical = self.canonical_hashes();
+        let branch = self.block_tree.branch(new_tip, |hash| canonical.contains_key(hash));
+        let ancestor_hash = branch
+            .first()
+            .map(|block| block.header.parent_block.clone())
+            .unwrap_or_else(|| new_tip.clone());
+        let ancestor_index = *canonical
+            .get(&ancestor_hash)
+            .ok_or(ConsensusError::UnknownParent(ancestor_hash))?;
+
+        let backup_state = self.snapshot_state();
+        let old_blocks = self.blocks.split_off(ancestor_index + 1);
+        let old_history = self.state_history.split_off(ancestor_index);
+        if let Some(ancestor_state) = old_history.first() {
+            self.restore_state(ancestor_state.clone());
+        }
+
+        for block in branch {
+            let block_hash = block.get_hash(self.properties.hasher);
+            // Branch was only checked against its parent when it arrived, not against its own state
+            let replay_res = <ConsensusEngine as Consensus>::verify_block(self, self.get_latest_block(), &block)
+                .map_err(ConsensusError::from)
+                .and_then(|()| self.apply_block(block));
+            if let Err(error) = replay_res {
+                self.blocks.truncate(ancestor_index + 1);
+                self.state_history.truncate(ancestor_index);
+                self.blocks.extend(old_blocks);
+                self.state_history.extend(old_history);
+                self.restore_state(backup_state);
+                self.block_tree.remove_with_descendants(&block_hash);
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fixed target from the next block on. Only meaningful while retargeting is off
+    pub fn set_fixed_target(&mut self, target: Target) {
+        let from_height = self.executing_height();
+        self.properties.target = target;
+        self.fixed_target_changes.retain(|(change_height, _)| *change_height < from_height);
+        self.fixed_target_changes.push((from_height, target));
+    }
+
+    /// Fixed target block at `height` must be sealed at. Target recorded in
+    /// genesis until first `set_fixed_target`
+    pub fn fixed_target_at(&self, height: BlockNumber) -> Target {
+        self.fixed_target_changes
+            .iter()
+            .rev()
+            .find(|(change_height, _)| *change_height <= height)
+            .map(|(_, target)| *target)
+            .unwrap_or(self.get_genesis_block().header.target)
+    }
+
+    /// Staking right after canonical `block`. None for block off the canonical
+    /// chain, since its state is only known once its branch is replayed
+    pub fn staking_after(&self, block: &Block) -> Option<&StakingStorage> {
+        let hasher = self.properties.hasher;
+        let block_hash = block.get_hash(hasher);
+        if block_hash == self.get_latest_block().get_hash(hasher) {
+            return Some(&self.staking);
+        }
+
+        // State kept before canonical block at `index + 1` is the one after block at `index`
+        let index = self.blocks
+            .iter()
+            .position(|canonical_block| canonical_block.get_hash(hasher) == block_hash)?;
+        self.state_history
+            .get(index)
+            .map(|state| &state.staking)
+    }
+
+    /// Index of every canonical block keyed by its hash
+    pub fn canonical_hashes(&self) -> HashMap<Hash, usize> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (block.get_hash(self.properties.hasher), index))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn test_properties() -> ChainProperties {
+        ChainProperties {
+            chain_id: 1,
+            address_prefix: crate::components::account::DEFAULT_ADDRESS_PREFIX,
+            exestinsial_deposit: 10,
+            target: Target::from_leading_zero_bits(1),
+            time_tolorant: Duration::from_secs(10),
+            consensus: ConsensusEngine::ProofOfWork,
+            sudo: None,
+            authorities: vec![],
+            slot_duration: Duration::from_secs(5),
+            unbonding_period: 10,
+            target_block_time: Duration::ZERO,
+            retarget_half_life: Duration::from_secs(600),
+            max_future_drift: Duration::from_secs(15),
+            hasher: HashAlgorithm::default(),
+        }
+    }
+
+    pub(crate) fn chain_with(properties: ChainProperties, stakers: Vec<(AccountId, Balance)>) -> Chain {
+        let genesis_config = GenesisConfig {
+            properties,
+            system_allowance: 1_000_000,
+            stakers,
+            timestamp: 0,
+        };
+        Chain::new("test chain".into(), genesis_config).expect("Stakes fit in system allowance")
+    }
+
+    pub(crate) fn test_chain() -> Chain {
+        chain_with(test_properties(), vec![])
+    }
+
+    fn signed(chain: &Chain, pair: &Pair, operation: Operation) -> Transaction {
+        let signer = pair.public().to_account_id();
+        Transaction {
+            operation,
+            nonce: chain.account_nonce(&signer),
+            initiator: Origin::Signed(signer),
+            chain_id: chain.properties.chain_id,
+            signature: None,
+        }
+        .sign(pair)
+    }
+
+    #[test]
+    fn signed_transaction_from_account_without_balance_can_not_be_replayed() {
+        let mut chain = test_chain();
+        let carol = Pair::from_seed(&[3; 32]);
+        let carol_id = carol.public().to_account_id();
+
+        let transaction = signed(&chain, &carol, Operation::Empty);
+        chain.execute(transaction.clone()).expect("First submission executes");
+
+        assert_eq!(chain.account_nonce(&carol_id), 1);
+        // Nonce alone does not make an account below existential deposit
+        assert!(!chain.accounts.contains_key(&carol_id));
+        assert_eq!(
+            chain.execute(transaction),
+            Err(DispatchError::StaleNonce { account: carol_id, expected: 1, found: 0 }),
+        );
+    }
+
+    fn funded_multisig(chain: &mut Chain, signatories: Vec<AccountId>, threshold: MultisigThreshold) -> AccountId {
+        let multisig = MultisigInfo::new(signatories.clone(), threshold).account_id();
+        chain.create_multisig(signatories, threshold).expect("Valid multisig");
+        chain.accounts.insert(multisig.clone(), AccountInfo { balance: 500 });
+        multisig
+    }
+
+    #[test]
+    fn replayed_multisig_proposal_does_not_execute_again() {
+        let mut chain = test_chain();
+        let alice = Pair::from_seed(&[1; 32]).public().to_account_id();
+        let carol = Pair::from_seed(&[3; 32]);
+        let multisig = funded_multisig(&mut chain, vec![alice.clone(), carol.public().to_account_id()], 1);
+
+        let call = Operation::TransferFund { sender: multisig.clone(), receiver: alice, amount: 100 };
+        let propose = signed(&chain, &carol, Operation::ProposeMultisig { multisig: multisig.clone(), call: Box::new(call) });
+        chain.execute(propose.clone()).expect("Proposal executes with single approval");
+        for _ in 0..3 {
+            assert!(matches!(chain.execute(propose.clone()), Err(DispatchError::StaleNonce { .. })));
+        }
+
+        assert_eq!(chain.accounts[&multisig].balance, 400);
+    }
+
+    #[test]
+    fn replayed_multisig_approval_does_not_execute_again() {
+        let mut chain = test_chain();
+        let alice = Pair::from_seed(&[1; 32]);
+        let carol = Pair::from_seed(&[3; 32]);
+        let alice_id = alice.public().to_account_id();
+        let multisig = funded_multisig(&mut chain, vec![alice_id.clone(), carol.public().to_account_id()], 2);
+
+        let call = Operation::TransferFund { sender: multisig.clone(), receiver: alice_id, amount: 100 };
+        let call_hash = crate::components::multisig::call_hash(&call);
+        let propose = signed(&chain, &alice, Operation::ProposeMultisig { multisig: multisig.clone(), call: Box::new(call) });
+        let approve = signed(&chain, &carol, Operation::ApproveMultisig { multisig: multisig.clone(), call_hash });
+        chain.execute(propose.clone()).expect("Proposal is stored");
+        chain.execute(approve.clone()).expect("Second approval executes the call");
+
+        assert!(matches!(chain.execute(propose), Err(DispatchError::StaleNonce { .. })));
+        assert!(matches!(chain.execute(approve), Err(DispatchError::StaleNonce { .. })));
+        assert_eq!(chain.accounts[&multisig].balance, 400);
+    }
+
+    // Sealed on top of `chain` tip without executing it
+    fn sealed_block(chain: &mut Chain, transactions: Vec<Transaction>) -> Block {
+        let mut block = Block::new(chain);
+        block.transactions = transactions;
+        crate::components::consensus::ProofOfWork::prepare_block(chain, &mut block).expect("Easy target is met");
+        block
+    }
+
+    fn mine_on(chain: &mut Chain, transactions: Vec<Transaction>) -> Block {
+        let block = sealed_block(chain, transactions);
+        chain.add_block(block.clone()).expect("Block is valid on its own branch");
+        block
+    }
+
+    #[test]
+    fn heavier_branch_takes_over_and_reverts_state_of_old_one() {
+        let mut chain = test_chain();
+        let mut fork = chain.clone();
+        let alice = Pair::from_seed(&[1; 32]);
+        let alice_id = alice.public().to_account_id();
+
+        let airdrop = signed(&chain, &alice, Operation::Airdrop { receiver: alice_id.clone(), amount: 100 });
+        mine_on(&mut chain, vec![airdrop]);
+        assert_eq!(chain.accounts[&alice_id].balance, 100);
+
+        let fork_blocks = [mine_on(&mut fork, vec![]), mine_on(&mut fork, vec![])];
+        chain.add_block(fork_blocks[0].clone()).expect("Equal work is kept as fork");
+        assert_eq!(chain.blocks.len(), 2);
+        assert!(chain.accounts.contains_key(&alice_id));
+
+        chain.add_block(fork_blocks[1].clone()).expect("Heavier branch is valid");
+        let hasher = chain.properties.hasher;
+        assert_eq!(chain.get_latest_block().get_hash(hasher), fork_blocks[1].get_hash(hasher));
+        assert_eq!(chain.blocks.len(), 3);
+        assert!(!chain.accounts.contains_key(&alice_id));
+    }
+
+    #[test]
+    fn failed_reorg_rolls_back_to_old_branch() {
+        let mut chain = test_chain();
+        let mut fork = chain.clone();
+        let alice = Pair::from_seed(&[1; 32]);
+        let carol = Pair::from_seed(&[3; 32]);
+        let alice_id = alice.public().to_account_id();
+
+        let airdrop = signed(&chain, &alice, Operation::Airdrop { receiver: alice_id.clone(), amount: 100 });
+        let old_tip = mine_on(&mut chain, vec![airdrop]);
+        let old_accounts = chain.accounts.clone();
+
+        // Carol has nothing to transfer, so second fork block fails once replayed
+        let fork_parent = mine_on(&mut fork, vec![]);
+        let transfer = signed(
+            &fork,
+            &carol,
+            Operation::TransferFund { sender: carol.public().to_account_id(), receiver: alice_id.clone(), amount: 50 },
+        );
+        let invalid_block = sealed_block(&mut fork, vec![transfer]);
+
+        chain.add_block(fork_parent.clone()).expect("Equal work is kept as fork");
+        assert!(matches!(
+            chain.add_block(invalid_block.clone()),
+            Err(ConsensusError::Transaction { tx_index: 0, .. }),
+        ));
+
+        let hasher = chain.properties.hasher;
+        assert_eq!(chain.get_latest_block().get_hash(hasher), old_tip.get_hash(hasher));
+        assert_eq!(chain.blocks.len(), 2);
+        assert_eq!(chain.state_history.len(), 1);
+        assert!(chain.accounts == old_accounts);
+        assert!(chain.block_tree.contains(&fork_parent.get_hash(hasher)));
+        assert!(!chain.block_tree.contains(&invalid_block.get_hash(hasher)));
+    }
+
+    #[test]
+    fn replay_holds_fixed_target_blocks_to_target_of_their_height() {
+        let mut chain = test_chain();
+        mine_on(&mut chain, vec![]);
+        chain.set_fixed_target(Target::from_leading_zero_bits(2));
+        let harder_block = mine_on(&mut chain, vec![]);
+        assert_eq!(harder_block.header.target, Target::from_leading_zero_bits(2));
+        chain.validate_from_genesis().expect("Every block met target of its height");
+
+        // Claiming easiest target skips the work
+        chain.blocks.last_mut().expect("Mined above").header.target = Target::MAX;
+        let validation_err = chain.validate_from_genesis().expect_err("Tip claims target it was not set to");
+        assert!(matches!(
+            *validation_err.error,
+            ConsensusError::Verification(BlockVerificationError::TargetMismatch { .. }),
+        ));
+    }
+
+    fn round_tripped(chain: &Chain) -> Chain {
+        let chain_as_json = serde_json::to_string(chain).expect("Chain is serializable");
+        serde_json::from_str(&chain_as_json).expect("Chain deserializes from its own json")
+    }
+
+    #[test]
+    fn chain_round_tripped_through_serde_validates_from_genesis() {
+        let mut chain = test_chain();
+        let alice = Pair::from_seed(&[1; 32]);
+        let alice_id = alice.public().to_account_id();
+
+        let airdrop = signed(&chain, &alice, Operation::Airdrop { receiver: alice_id, amount: 100 });
+        mine_on(&mut chain, vec![airdrop]);
+        let empty = signed(&chain, &alice, Operation::Empty);
+        mine_on(&mut chain, vec![empty]);
+
+        assert_eq!(round_tripped(&chain).validate_from_genesis(), Ok(()));
+    }
+
+    #[test]
+    fn chain_round_tripped_through_serde_reverts_to_its_own_state_on_reorg() {
+        let mut chain = test_chain();
+        let mut fork = chain.clone();
+        let alice = Pair::from_seed(&[1; 32]);
+        let alice_id = alice.public().to_account_id();
+
+        let airdrop = signed(&chain, &alice, Operation::Airdrop { receiver: alice_id.clone(), amount: 100 });
+        mine_on(&mut chain, vec![airdrop]);
+        let mut chain = round_tripped(&chain);
+
+        for fork_block in [mine_on(&mut fork, vec![]), mine_on(&mut fork, vec![])] {
+            chain.add_block(fork_block).expect("Fork block is valid");
+        }
+        assert_eq!(chain.blocks.len(), 3);
+        assert!(!chain.accounts.contains_key(&alice_id));
+        assert_eq!(chain.account_nonce(&alice_id), 0);
     }
 }
@@ -0,0 +1,216 @@
+use std::sync::atomic::AtomicU16;
+use std::sync::atomic::Ordering;
+
+use crate::components::hash::Hash;
+use crate::components::hasher::hash_algorithm;
+use crate::components::hasher::HashAlgorithm;
+use crate::components::AddressPrefix;
+use crate::components::SU_HASHER_LEN;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+pub const DEFAULT_ADDRESS_PREFIX: AddressPrefix = 42;
+pub const MAX_ADDRESS_PREFIX: AddressPrefix = 16_383;
+
+const CHECKSUM_LEN: usize = 2;
+const CHECKSUM_DOMAIN: &[u8] = b"SS58PRE";
+
+// Prefix used to encode and accept addresses. Set by the running chain
+// the same way a node would set it once from its chain spec
+static ADDRESS_PREFIX: AtomicU16 = AtomicU16::new(DEFAULT_ADDRESS_PREFIX);
+
+pub fn set_address_prefix(prefix: AddressPrefix) {
+    ADDRESS_PREFIX.store(prefix, Ordering::Relaxed);
+}
+
+pub fn address_prefix() -> AddressPrefix {
+    ADDRESS_PREFIX.load(Ordering::Relaxed)
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+struct PrettyAddress(String);
+
+impl From<AccountId> for PrettyAddress {
+    fn from(src: AccountId) -> Self {
+        PrettyAddress(src.to_address(address_prefix()))
+    }
+}
+
+impl TryFrom<PrettyAddress> for AccountId {
+    type Error = String;
+
+    fn try_from(src: PrettyAddress) -> Result<Self, Self::Error> {
+        src.0.parse()
+    }
+}
+
+impl core::str::FromStr for AccountId {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let (prefix, account_id) = AccountId::from_address(src)?;
+        let expected_prefix = address_prefix();
+        if prefix != expected_prefix {
+            Err(format!("Address {src} is for network prefix {prefix} but chain uses {expected_prefix}"))?;
+        }
+
+        Ok(account_id)
+    }
+}
+
+/// Account identifier. Displayed and parsed as SS58-like checksummed address:
+/// base58(prefix ++ account bytes ++ checksum) where checksum is first two
+/// bytes of hash("SS58PRE" ++ prefix ++ account bytes)
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "PrettyAddress")]
+#[serde(try_from = "PrettyAddress")]
+pub struct AccountId([u8; SU_HASHER_LEN]);
+
+impl core::fmt::Display for AccountId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_address(address_prefix()))
+    }
+}
+
+impl core::fmt::Debug for AccountId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AccountId({self})")
+    }
+}
+
+impl From<Hash> for AccountId {
+    fn from(src: Hash) -> Self {
+        AccountId(*src.as_bytes())
+    }
+}
+
+impl AccountId {
+    pub fn raw(account_bytes: [u8; SU_HASHER_LEN]) -> Self {
+        AccountId(account_bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SU_HASHER_LEN] {
+        &self.0
+    }
+
+    pub fn to_address(&self, prefix: AddressPrefix) -> String {
+        self.to_address_under(prefix, hash_algorithm())
+    }
+
+    /// Encode address with checksum made under `hasher`
+    /// rather than the running chain's
+    pub fn to_address_under(&self, prefix: AddressPrefix, hasher: HashAlgorithm) -> String {
+        let mut payload = encode_prefix(prefix);
+        payload.extend_from_slice(&self.0);
+        let checksum = address_checksum(&payload, hasher);
+        payload.extend_from_slice(&checksum);
+
+        bs58::encode(payload).into_string()
+    }
+
+    pub fn from_address(address: &str) -> Result<(AddressPrefix, Self), String> {
+        Self::from_address_under(address, hash_algorithm())
+    }
+
+    /// Decode address whose checksum was made under `hasher`
+    /// rather than the running chain's
+    pub fn from_address_under(address: &str, hasher: HashAlgorithm) -> Result<(AddressPrefix, Self), String> {
+        let payload = bs58::decode(address.trim())
+            .into_vec()
+            .map_err(|e| format!("Address {address} is not valid base58: {e}"))?;
+        let (prefix_len, prefix) = decode_prefix(&payload)
+            .ok_or_else(|| format!("Address {address} has invalid network prefix"))?;
+
+        if payload.len() != prefix_len + SU_HASHER_LEN + CHECKSUM_LEN {
+            Err(format!("Address {address} has unexpected length"))?;
+        }
+        let (body, checksum) = payload.split_at(prefix_len + SU_HASHER_LEN);
+        if address_checksum(body, hasher) != checksum {
+            Err(format!("Address {address} has invalid checksum. Is it mistyped?"))?;
+        }
+
+        let account_bytes = body[prefix_len..]
+            .try_into()
+            .expect("Length checked above");
+        Ok((prefix, AccountId(account_bytes)))
+    }
+}
+
+fn address_checksum(body: &[u8], hasher: HashAlgorithm) -> [u8; CHECKSUM_LEN] {
+    let checksum_hash = Hash::raw(hasher.digest(&[CHECKSUM_DOMAIN, body].concat()));
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    checksum.copy_from_slice(&checksum_hash.as_bytes()[..CHECKSUM_LEN]);
+    checksum
+}
+
+// Same layout as SS58: prefix below 64 takes one byte, upto 16383 takes two
+fn encode_prefix(prefix: AddressPrefix) -> Vec<u8> {
+    assert!(prefix <= MAX_ADDRESS_PREFIX, "Address prefix must not exceed {MAX_ADDRESS_PREFIX}");
+    if prefix < 64 {
+        vec![prefix as u8]
+    } else {
+        let first = ((prefix & 0b0000_0000_1111_1100) as u8) >> 2;
+        let second = ((prefix >> 8) as u8) | (((prefix & 0b0000_0000_0000_0011) as u8) << 6);
+        vec![first | 0b0100_0000, second]
+    }
+}
+
+fn decode_prefix(payload: &[u8]) -> Option<(usize, AddressPrefix)> {
+    match payload.first()? {
+        first @ 0..=63 => Some((1, *first as AddressPrefix)),
+        first @ 64..=127 => {
+            let second = payload.get(1)?;
+            let lower = (first << 2) | (second >> 6);
+            let upper = second & 0b0011_1111;
+            Some((2, (lower as AddressPrefix) | ((upper as AddressPrefix) << 8)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_round_trips_at_width_boundaries() {
+        for (prefix, encoded) in [
+            (0, vec![0x00]),
+            (63, vec![0x3f]),
+            (64, vec![0x50, 0x00]),
+            (16_383, vec![0x7f, 0xff]),
+        ] {
+            assert_eq!(encode_prefix(prefix), encoded);
+            assert_eq!(decode_prefix(&encoded), Some((encoded.len(), prefix)));
+        }
+    }
+
+    #[test]
+    fn reserved_prefix_bytes_are_rejected() {
+        assert_eq!(decode_prefix(&[0x80]), None);
+        assert_eq!(decode_prefix(&[0x50]), None);
+        assert_eq!(decode_prefix(&[]), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn prefix_above_max_is_not_encoded() {
+        encode_prefix(MAX_ADDRESS_PREFIX + 1);
+    }
+
+    #[test]
+    fn address_round_trips_and_catches_typo() {
+        let account_id = AccountId::raw([7; SU_HASHER_LEN]);
+        for prefix in [0, 42, 63, 64, MAX_ADDRESS_PREFIX] {
+            let address = account_id.to_address(prefix);
+            assert_eq!(AccountId::from_address(&address), Ok((prefix, account_id.clone())));
+        }
+
+        let address = account_id.to_address(42);
+        let typo = if address.ends_with('1') { "2" } else { "1" };
+        let mistyped = format!("{}{typo}", &address[..address.len() - 1]);
+        assert!(AccountId::from_address(&mistyped).is_err());
+    }
+}
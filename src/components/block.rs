@@ -1,13 +1,20 @@
 use crate::components::current_timestamp;
 use crate::components::hash::Hash;
+use crate::components::hasher::HashAlgorithm;
 use crate::components::Nonce;
+use crate::components::ExtraNonce;
 use crate::components::BlockNumber;
 use crate::chain::Chain;
+use crate::chain::GenesisConfig;
 use crate::components::transaction::Transaction;
 use crate::components::transaction::TransactionCollection;
-use crate::components::transaction::TransactionResult;
-use crate::components::consensus::ProofOfWork;
+use crate::components::consensus::ConsensusEngine;
 use crate::components::consensus::Consensus;
+use crate::components::consensus::ConsensusResult;
+use crate::components::difficulty::Target;
+use crate::components::difficulty::Work;
+use crate::components::signature::PublicKey;
+use crate::components::signature::Signature;
 
 use serde::Deserialize;
 use serde::Serialize;
@@ -17,9 +24,25 @@ pub type BlockCollection = Vec<Block>;
 #[derive(Clone, Eq, Serialize, Deserialize)]
 pub struct BlockHeader {
     pub parent_block: Hash,
+    // Merkle root of transaction hashes. Header commits to transactions only through it
+    pub transactions_root: Hash,
     pub nonce: Nonce,
+    // Widens seal search once every `nonce` is tried
+    #[serde(default)]
+    pub extra_nonce: ExtraNonce,
     pub height: BlockNumber,
     pub timestamp: u64,
+    // Target the block was sealed at. Engines without work use Target::MAX
+    pub target: Target,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seal: Option<AuthoritySeal>,
+}
+
+/// Signature of block author over `Block::pre_seal_hash()`
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthoritySeal {
+    pub author: PublicKey,
+    pub signature: Signature,
 }
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -41,54 +64,111 @@ impl PartialEq for BlockHeader {
         ( self.parent_block == other.parent_block )
             && (self.height == other.height)
             && (self.nonce == other.nonce )
+            && (self.extra_nonce == other.extra_nonce)
+    }
+}
+
+impl BlockHeader {
+    // Fixed size encoding of every field except seal, so hashing
+    // costs the same however many transactions block carries
+    fn pre_seal_bytes(&self) -> Vec<u8> {
+        [
+            self.parent_block.as_bytes().as_slice(),
+            self.transactions_root.as_bytes(),
+            &self.height.to_le_bytes(),
+            &self.timestamp.to_le_bytes(),
+            &self.target.to_bytes(),
+            &self.nonce.to_le_bytes(),
+            &self.extra_nonce.to_le_bytes(),
+        ].concat()
+    }
+
+    /// Hash of header without its seal. This is what the author signs
+    pub fn pre_seal_hash(&self, hasher: HashAlgorithm) -> Hash {
+        Hash::new_under(hasher, self.pre_seal_bytes())
+    }
+
+    pub fn get_hash(&self, hasher: HashAlgorithm) -> Hash {
+        let mut header_bytes = self.pre_seal_bytes();
+        if let Some(seal) = &self.seal {
+            header_bytes.extend_from_slice(seal.author.as_bytes());
+            header_bytes.extend_from_slice(seal.signature.as_bytes());
+        }
+
+        Hash::new_under(hasher, header_bytes)
     }
 }
 
 impl Block {
     pub fn new(chain: &Chain) -> Self {
+        let hasher = chain.properties.hasher;
         let parent_block = chain.get_latest_block();
         Block {
             header: BlockHeader {
-                parent_block: parent_block.get_hash(),
+                parent_block: parent_block.get_hash(hasher),
+                transactions_root: Self::transactions_root(hasher, &[]),
                 nonce: 0,
+                extra_nonce: 0,
                 height: parent_block.header.height + 1,
                 timestamp: current_timestamp(),
+                target: Target::MAX,
+                seal: None,
             },
             transactions: vec![],
         }
     }
 
-    pub fn create_and_add(chain: &mut Chain, transactions: Vec<Transaction>) -> TransactionResult {
-        let mut new_block = Self::new(&chain);
+    pub fn create_and_add(chain: &mut Chain, transactions: Vec<Transaction>) -> ConsensusResult {
+        let mut new_block = Self::new(chain);
         new_block.transactions = transactions;
-        <ProofOfWork as Consensus>::prepare_block(chain, &mut new_block)
-            .map_err(|e| format!("While preparing block: {e}"))?;
+        <ConsensusEngine as Consensus>::prepare_block(chain, &mut new_block)?;
 
         chain.add_block(new_block)
-            .map_err(|e| format!("While adding block: {e}").into())
     }
 
-    pub fn get_genesis() -> Self {
+    /// Genesis is derived from config alone. Its parent hash commits to the config
+    /// so chains set up differently never share genesis
+    pub fn get_genesis(genesis_config: &GenesisConfig) -> Self {
         let block_height: BlockNumber = 1u32.into();
-        let parent_hash: Hash = Hash::raw([0u8; 32]);
-        let nonce: Nonce = 0u32.into();
+        let parent_hash: Hash = genesis_config.get_hash();
+        let nonce: Nonce = 0;
         let transactions = vec![];
 
         Block {
             header: BlockHeader {
                 parent_block: parent_hash,
+                transactions_root: Self::transactions_root(genesis_config.properties.hasher, &transactions),
                 nonce,
+                extra_nonce: 0,
                 height: block_height,
-                timestamp: current_timestamp(),
+                timestamp: genesis_config.timestamp,
+                target: genesis_config.properties.target,
+                seal: None,
             },
             transactions,
         }
     }
 
-    pub fn get_hash(&self) -> Hash {
-        let block_as_json = serde_json::to_string(self)
-            .expect("Cannot represent Block{} as json string");
-        
-        Hash::new(block_as_json.as_bytes())
+    /// Block is identified by hash of its header alone
+    pub fn get_hash(&self, hasher: HashAlgorithm) -> Hash {
+        self.header.get_hash(hasher)
+    }
+
+    pub fn transactions_root(hasher: HashAlgorithm, transactions: &[Transaction]) -> Hash {
+        Hash::merkle_root(hasher, transactions.iter().map(|transaction| transaction.get_hash(hasher)))
+    }
+
+    /// Record root of current transactions in header. Must be called once
+    /// transactions are final and before block is sealed
+    pub fn commit_transactions(&mut self, hasher: HashAlgorithm) {
+        self.header.transactions_root = Self::transactions_root(hasher, &self.transactions);
+    }
+
+    pub fn work(&self) -> Work {
+        self.header.target.work()
+    }
+
+    pub fn pre_seal_hash(&self, hasher: HashAlgorithm) -> Hash {
+        self.header.pre_seal_hash(hasher)
     }
 }
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::components::block::Block;
+use crate::components::difficulty::Work;
+use crate::components::hash::Hash;
+
+#[derive(Clone)]
+pub struct TreeEntry {
+    pub block: Block,
+    // Work of every block from genesis upto and including this one
+    pub cumulative_work: Work,
+}
+
+/// Every known block, canonical or not, keyed by its hash
+#[derive(Clone, Default)]
+pub struct BlockTree {
+    entries: HashMap<Hash, TreeEntry>,
+}
+
+impl BlockTree {
+    /// `block_hash` must be hash of `block` under the chain's hasher
+    pub fn insert(&mut self, block_hash: Hash, block: Block, cumulative_work: Work) {
+        let entry = TreeEntry {
+            block,
+            cumulative_work,
+        };
+        self.entries.insert(block_hash, entry);
+    }
+
+    pub fn get(&self, hash: &Hash) -> Option<&TreeEntry> {
+        self.entries.get(hash)
+    }
+
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.entries.contains_key(hash)
+    }
+
+    /// Blocks no other block builds on, keyed by their hash
+    pub fn leaves(&self) -> Vec<(&Hash, &TreeEntry)> {
+        let parents = self.entries
+            .values()
+            .map(|entry| &entry.block.header.parent_block)
+            .collect::<HashSet<_>>();
+
+        self.entries
+            .iter()
+            .filter(|(hash, _)| !parents.contains(hash))
+            .collect()
+    }
+
+    /// Block at `from` followed by its ancestors, newest first, as far as they are known
+    pub fn ancestry<'tree>(&'tree self, from: &Hash) -> impl Iterator<Item = &'tree Block> {
+        let mut current = self.entries.get(from);
+        std::iter::from_fn(move || {
+            let entry = current?;
+            current = self.entries.get(&entry.block.header.parent_block);
+            Some(&entry.block)
+        })
+    }
+
+    /// Blocks from `tip` back to first block accepted by `stop_at`, oldest first.
+    /// Block matching `stop_at` is not included
+    pub fn branch(&self, tip: &Hash, stop_at: impl Fn(&Hash) -> bool) -> Vec<Block> {
+        let mut branch = vec![];
+        let mut current = tip;
+        while !stop_at(current) {
+            let Some(entry) = self.entries.get(current) else {
+                break;
+            };
+            branch.push(entry.block.clone());
+            current = &entry.block.header.parent_block;
+        }
+        branch.reverse();
+
+        branch
+    }
+
+    /// Remove block and everything built on it
+    pub fn remove_with_descendants(&mut self, hash: &Hash) {
+        let mut to_remove = vec![hash.clone()];
+        while let Some(removing) = to_remove.pop() {
+            self.entries.remove(&removing);
+            to_remove.extend(
+                self.entries
+                    .iter()
+                    .filter(|(_, entry)| entry.block.header.parent_block == removing)
+                    .map(|(child, _)| child.clone()),
+            );
+        }
+    }
+}
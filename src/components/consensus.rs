@@ -2,108 +2,694 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use crate::chain::{Chain, ChainProperties};
+use crate::components::block::AuthoritySeal;
 use crate::components::block::Block;
-use crate::components::Nonce;
-use crate::components::transaction::TransactionResult;
+use crate::components::error::BlockVerificationError;
+use crate::components::error::ConsensusError;
+use crate::components::hash::Hash;
+use crate::components::hasher::HashAlgorithm;
+use crate::components::difficulty::Target;
+use crate::components::signature::Pair;
+use crate::components::signature::PublicKey;
+use crate::components::staking::StakingStorage;
+use crate::components::AccountId;
 
 use super::current_timestamp;
 
+pub type ConsensusResult = Result<(), ConsensusError>;
+pub type VerificationResult = Result<(), BlockVerificationError>;
+pub type Slot = u64;
+
+const SLOT_LEADER_DOMAIN: &[u8] = b"SuchainSlotLeader";
+// Number of latest blocks whose median timestamp a new block may not precede
+const MEDIAN_TIME_SPAN: usize = 11;
+
 pub trait Consensus {
-    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> TransactionResult;
-    fn verify_new_block(last_block: &Block, new_block: &Block, properties: &ChainProperties) -> TransactionResult;
-    fn add_new_block(chain: &mut Chain, new_block: Block) -> TransactionResult;
+    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> ConsensusResult;
+    /// Rules block must satisfy whenever it is checked, independent of local clock
+    fn verify_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult;
+
+    /// Block arriving now must also be fresh
+    fn verify_new_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+        Self::verify_block(chain, last_block, new_block)?;
+        verify_freshness(new_block, &chain.properties)
+    }
+
+    fn add_new_block(chain: &mut Chain, new_block: Block) -> ConsensusResult {
+        let parent_block = chain.get_latest_block();
+        
+        Self::verify_new_block(chain, parent_block, &new_block)?;
+
+        chain.apply_block(new_block)
+    }
+}
+
+/// Engine selected per chain in `ChainProperties`. Dispatches to the
+/// implementation of selected engine so chain never names one directly.
+/// New engine only need a variant here
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsensusEngine {
+    #[default]
+    ProofOfWork,
+    ProofOfAuthority,
+    ProofOfStake,
+}
+
+impl Consensus for ConsensusEngine {
+    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> ConsensusResult {
+        match chain.properties.consensus {
+            ConsensusEngine::ProofOfWork => <ProofOfWork as Consensus>::prepare_block(chain, new_block),
+            ConsensusEngine::ProofOfAuthority => <ProofOfAuthority as Consensus>::prepare_block(chain, new_block),
+            ConsensusEngine::ProofOfStake => <ProofOfStake as Consensus>::prepare_block(chain, new_block),
+        }
+    }
+
+    fn verify_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+        match chain.properties.consensus {
+            ConsensusEngine::ProofOfWork => <ProofOfWork as Consensus>::verify_block(chain, last_block, new_block),
+            ConsensusEngine::ProofOfAuthority => <ProofOfAuthority as Consensus>::verify_block(chain, last_block, new_block),
+            ConsensusEngine::ProofOfStake => <ProofOfStake as Consensus>::verify_block(chain, last_block, new_block),
+        }
+    }
+
+    fn verify_new_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+        match chain.properties.consensus {
+            ConsensusEngine::ProofOfWork => <ProofOfWork as Consensus>::verify_new_block(chain, last_block, new_block),
+            ConsensusEngine::ProofOfAuthority => <ProofOfAuthority as Consensus>::verify_new_block(chain, last_block, new_block),
+            ConsensusEngine::ProofOfStake => <ProofOfStake as Consensus>::verify_new_block(chain, last_block, new_block),
+        }
+    }
+
+    fn add_new_block(chain: &mut Chain, new_block: Block) -> ConsensusResult {
+        match chain.properties.consensus {
+            ConsensusEngine::ProofOfWork => <ProofOfWork as Consensus>::add_new_block(chain, new_block),
+            ConsensusEngine::ProofOfAuthority => <ProofOfAuthority as Consensus>::add_new_block(chain, new_block),
+            ConsensusEngine::ProofOfStake => <ProofOfStake as Consensus>::add_new_block(chain, new_block),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProofOfWork;
 
-impl Consensus for ProofOfWork {
-    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> TransactionResult {
-        let difficulty = chain.properties.difficulty;
+impl ProofOfWork {
+    /// Target for block built on `parent_block`. Anchored at first block after
+    /// genesis, since genesis timestamp is fixed by config rather than made by
+    /// a miner, and moved by how far parent is from schedule. Without
+    /// retargeting it is whatever `set_difficulty` had set at that height
+    pub fn expected_target(chain: &Chain, parent_block: &Block) -> Target {
+        let properties = &chain.properties;
+        let block_time = properties.target_block_time.as_secs();
+        if block_time == 0 {
+            return chain.fixed_target_at(parent_block.header.height + 1);
+        }
+
+        // Anchor itself is sealed at target recorded in genesis
+        let Some(anchor_block) = Self::retarget_anchor(chain, parent_block) else {
+            return chain.get_genesis_block().header.target;
+        };
+        let anchor_header = &anchor_block.header;
+        let parent_header = &parent_block.header;
+        let elapsed = i128::from(parent_header.timestamp) - i128::from(anchor_header.timestamp);
+        let scheduled = i128::from(parent_header.height.saturating_sub(anchor_header.height)) * i128::from(block_time);
+
+        anchor_header.target.scheduled(elapsed - scheduled, properties.retarget_half_life.as_secs())
+    }
+
+    // First block after genesis on branch of `parent_block`. None while parent is genesis
+    fn retarget_anchor<'chain>(chain: &'chain Chain, parent_block: &Block) -> Option<&'chain Block> {
+        let genesis_hash = chain.genesis_hash();
+        let hasher = chain.properties.hasher;
+        let parent_hash = parent_block.get_hash(hasher);
+        if &parent_hash == genesis_hash {
+            return None;
+        }
+
+        // Canonical parent shares canonical anchor, no need to walk the tree
+        let canonical_index = parent_block.header.height.checked_sub(chain.get_genesis_block().header.height)?;
+        let is_canonical = chain.blocks
+            .get(canonical_index as usize)
+            .is_some_and(|canonical_block| canonical_block.get_hash(hasher) == parent_hash);
+        if is_canonical {
+            return chain.blocks.get(1);
+        }
+
+        chain.block_tree
+            .ancestry(&parent_hash)
+            .find(|block| &block.header.parent_block == genesis_hash)
+    }
+
+    /// Point block at the tip, commit its transactions and return target
+    /// its seal must meet. Search itself does not need the chain
+    pub fn prepare_template(chain: &Chain, new_block: &mut Block) -> Target {
+        new_block.commit_transactions(chain.properties.hasher);
         let latest_block = chain.get_latest_block();
+        let target = Self::expected_target(chain, latest_block);
+        new_block.header.target = target;
 
-        let latest_block_hash = latest_block.get_hash();
+        let latest_block_hash = latest_block.get_hash(chain.properties.hasher);
         new_block.header.parent_block = latest_block_hash;
+        new_block.header.timestamp = next_timestamp(chain, latest_block);
+
+        target
+    }
+}
+
+impl Consensus for ProofOfWork {
+    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> ConsensusResult {
+        let target = Self::prepare_template(chain, new_block);
+        let ticket = chain.miner.ticket();
+        chain.miner.mine(new_block, target, chain.properties.hasher, ticket)?;
 
-        let lower_time_bound = new_block.header.timestamp;
-        let upper_time_vound = lower_time_bound
-            .checked_add(chain.properties.time_tolorant.as_secs())
-            .expect("oops! is this of time? I though time was eternal :(");
-        let time_range = lower_time_bound..upper_time_vound;
-
-        for produced_time in time_range{
-            new_block.header.timestamp = produced_time;
-            let nonce_range = Nonce::MIN..Nonce::MAX;
-
-            for nonce in nonce_range.clone() {
-                if new_block
-                    .get_hash()
-                    .difficulty_verified(difficulty)
-                {
-                    return Ok(());
-                }
-                new_block.header.nonce = nonce;
-            }
+        Ok(())
+    }
+
+    fn verify_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+        verify_parent_hash(chain, last_block, new_block)?;
+        verify_height(last_block, new_block)?;
+        verify_transactions_root(chain, new_block)?;
+        verify_transaction_signatures(new_block)?;
+        verify_timestamp(chain, last_block, new_block)?;
+
+        // Verify the work done. Target follows from ancestry alone, or from
+        // recorded `set_difficulty` changes while retargeting is off
+        let target = new_block.header.target;
+        verify_target(new_block, Self::expected_target(chain, last_block))?;
+        let new_block_hash = new_block.get_hash(chain.properties.hasher);
+        if !target.is_met_by(&new_block_hash) {
+            Err(BlockVerificationError::DifficultyNotMet { target })?;
         }
 
-        Self::verify_new_block(latest_block, new_block, &chain.properties)
-            .map_err(|e| format!("Post verification error: {e}"))?;
+        Ok(())
+    }
+}
 
-        Err("Cannot pass difficulty with any value".into())
+fn verify_parent_hash(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+    let written_parent_hash = &new_block.header.parent_block;
+    let expected_parent_hash = last_block.get_hash(chain.properties.hasher);
+    if written_parent_hash != &expected_parent_hash {
+        Err(BlockVerificationError::ParentHashMismatch {
+            expected: expected_parent_hash,
+            found: written_parent_hash.clone(),
+        })?;
     }
 
-    fn add_new_block(chain: &mut Chain, new_block: Block) -> TransactionResult {
-        let parent_block = chain.get_latest_block();
-        
-        Self::verify_new_block(&parent_block, &new_block, &chain.properties)
-            .map_err(|e| format!("Verifying new block: {e}"))?;
-
-        // A simple rollback mechanism
-        let old_accounts_state = chain.accounts.clone();
-
-        for (tx_index, transaction) in new_block.transactions.iter().enumerate() {
-            let transaction_result = chain.execute(transaction.clone());
-
-            if let Err(tx_err) = transaction_result {
-                chain.accounts = old_accounts_state;
-                return Err(
-                    format!(
-                        "Error while performing {tx_index}th transaction.\
-                        Transaction: {transaction:?}.\
-                        Error: {tx_err}"
-                    ).into()
-                );
-            }
+    Ok(())
+}
+
+fn verify_height(last_block: &Block, new_block: &Block) -> VerificationResult {
+    let expected = last_block.header.height.saturating_add(1);
+    let found = new_block.header.height;
+    if found != expected {
+        Err(BlockVerificationError::HeightMismatch { expected, found })?;
+    }
+
+    Ok(())
+}
+
+fn verify_transactions_root(chain: &Chain, new_block: &Block) -> VerificationResult {
+    let expected = Block::transactions_root(chain.properties.hasher, &new_block.transactions);
+    let found = &new_block.header.transactions_root;
+    if found != &expected {
+        Err(BlockVerificationError::TransactionsRootMismatch {
+            expected,
+            found: found.clone(),
+        })?;
+    }
+
+    Ok(())
+}
+
+// Every transaction must be signed by whom it claims
+fn verify_transaction_signatures(new_block: &Block) -> VerificationResult {
+    for (tx_index, transaction) in new_block.transactions.iter().enumerate() {
+        transaction.verify_signature()
+            .map_err(|error| BlockVerificationError::InvalidSignature { tx_index, error })?;
+    }
+
+    Ok(())
+}
+
+fn verify_target(new_block: &Block, expected: Target) -> VerificationResult {
+    let found = new_block.header.target;
+    if found != expected {
+        Err(BlockVerificationError::TargetMismatch { expected, found })?;
+    }
+
+    Ok(())
+}
+
+// Several blocks may share a second with their parent, but not with most
+// of recent blocks. So time keeps moving even if one block stamps it back
+fn verify_timestamp(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+    let timestamp = new_block.header.timestamp;
+
+    let parent_timestamp = last_block.header.timestamp;
+    if timestamp < parent_timestamp {
+        Err(BlockVerificationError::TimestampBeforeParent { parent_timestamp, timestamp })?;
+    }
+
+    let median_time_past = median_time_past(chain, last_block);
+    if timestamp <= median_time_past {
+        Err(BlockVerificationError::TimestampNotAfterMedianTimePast { median_time_past, timestamp })?;
+    }
+
+    Ok(())
+}
+
+/// Earliest timestamp acceptable for block built on `parent_block`.
+/// Local clock is used unless chain is already ahead of it
+fn next_timestamp(chain: &Chain, parent_block: &Block) -> u64 {
+    let earliest = median_time_past(chain, parent_block).saturating_add(1);
+    current_timestamp()
+        .max(earliest)
+        .max(parent_block.header.timestamp)
+}
+
+// Median timestamp of `last_block` and its recent ancestors. Unlike
+// parent alone, a single block with skewed clock can not move it
+fn median_time_past(chain: &Chain, last_block: &Block) -> u64 {
+    let mut timestamps = chain.block_tree
+        .ancestry(&last_block.get_hash(chain.properties.hasher))
+        .take(MEDIAN_TIME_SPAN)
+        .map(|block| block.header.timestamp)
+        .collect::<Vec<_>>();
+    if timestamps.is_empty() {
+        return last_block.header.timestamp;
+    }
+    timestamps.sort_unstable();
+
+    timestamps[timestamps.len() / 2]
+}
+
+// Checks against local clock. Only block arriving now is held to them,
+// a historical block may be of any age
+fn verify_freshness(new_block: &Block, properties: &ChainProperties) -> VerificationResult {
+    let block_timestamp = new_block.header.timestamp;
+    let now = current_timestamp();
+
+    let max_timestamp = now.saturating_add(properties.max_future_drift.as_secs());
+    if block_timestamp > max_timestamp {
+        Err(BlockVerificationError::TimestampTooFarInFuture {
+            timestamp: block_timestamp,
+            max_timestamp,
+        })?;
+    }
+
+    let timestamp_difference = now.saturating_sub(block_timestamp);
+    let maximum_acceptable_difference = properties.time_tolorant.as_secs();
+    if timestamp_difference > maximum_acceptable_difference {
+        Err(BlockVerificationError::BlockTooOld {
+            age: timestamp_difference,
+            maximum_age: maximum_acceptable_difference,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Slots are fixed windows of `slot_duration` since unix epoch
+pub fn slot_at(timestamp: u64, properties: &ChainProperties) -> Slot {
+    timestamp / properties.slot_duration.as_secs().max(1)
+}
+
+// Only one block per slot. Genesis is the only unsealed block
+// so any slot may follow it
+fn verify_slot_advanced(last_block: &Block, new_block: &Block, properties: &ChainProperties) -> Result<Slot, BlockVerificationError> {
+    let slot = slot_at(new_block.header.timestamp, properties);
+    if last_block.header.seal.is_some() {
+        let parent_slot = slot_at(last_block.header.timestamp, properties);
+        if slot <= parent_slot {
+            Err(BlockVerificationError::SlotNotAdvanced { parent_slot, slot })?;
+        }
+    }
+
+    Ok(slot)
+}
+
+fn seal_block(new_block: &mut Block, author_key: &Pair, hasher: HashAlgorithm) {
+    new_block.header.seal = None;
+    let signature = author_key.sign(new_block.pre_seal_hash(hasher).as_bytes());
+    new_block.header.seal = Some(AuthoritySeal {
+        author: author_key.public(),
+        signature,
+    });
+}
+
+// Seal is present and signed by the author it names. Whether that
+// author may seal the slot is left to the engine
+fn verify_seal(new_block: &Block, hasher: HashAlgorithm) -> Result<&AuthoritySeal, BlockVerificationError> {
+    let seal = new_block.header.seal
+        .as_ref()
+        .ok_or(BlockVerificationError::MissingSeal)?;
+    seal.author
+        .verify(new_block.pre_seal_hash(hasher).as_bytes(), &seal.signature)
+        .map_err(BlockVerificationError::InvalidSeal)?;
+
+    Ok(seal)
+}
+
+/// Authorities take turn sealing blocks. Each slot of `slot_duration`
+/// belongs to one authority in round-robin order
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofOfAuthority;
+
+impl ProofOfAuthority {
+    pub fn slot_author(slot: Slot, properties: &ChainProperties) -> Option<&PublicKey> {
+        let authorities = &properties.authorities;
+        if authorities.is_empty() {
+            return None;
         }
+        let author_index = slot % authorities.len() as u64;
+        authorities.get(author_index as usize)
+    }
+}
 
-        chain.blocks.push(new_block);
+impl Consensus for ProofOfAuthority {
+    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> ConsensusResult {
+        let latest_block = chain.get_latest_block();
+        new_block.header.parent_block = latest_block.get_hash(chain.properties.hasher);
+        new_block.header.timestamp = next_timestamp(chain, latest_block);
+        new_block.commit_transactions(chain.properties.hasher);
+
+        let slot = slot_at(new_block.header.timestamp, &chain.properties);
+        let slot_author = Self::slot_author(slot, &chain.properties)
+            .ok_or(BlockVerificationError::NoAuthorities)?;
+        let author_key = chain.author_key
+            .as_ref()
+            .ok_or(ConsensusError::NoAuthorKey)?;
+        if &author_key.public() != slot_author {
+            Err(ConsensusError::NotAuthorForSlot {
+                slot,
+                author: slot_author.clone(),
+            })?;
+        }
+        seal_block(new_block, author_key, chain.properties.hasher);
+
+        Self::verify_new_block(chain, latest_block, new_block)?;
 
         Ok(())
     }
 
-    fn verify_new_block(last_block: &Block, new_block: &Block, properties: &ChainProperties) -> TransactionResult {
-        // Verify the parent hash
-        let written_parent_hash = &new_block.header.parent_block;
-        let expected_parent_hash = last_block.get_hash();
-        if written_parent_hash != &expected_parent_hash {
-            Err("mismatched parent hash in new block header")?;
+    fn verify_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+        let properties = &chain.properties;
+        verify_parent_hash(chain, last_block, new_block)?;
+        verify_height(last_block, new_block)?;
+        verify_transactions_root(chain, new_block)?;
+        verify_transaction_signatures(new_block)?;
+        verify_timestamp(chain, last_block, new_block)?;
+        // No work is done so block must not claim any
+        verify_target(new_block, Target::MAX)?;
+        let slot = verify_slot_advanced(last_block, new_block, properties)?;
+
+        // Verify the right authority sealed it
+        let seal = verify_seal(new_block, properties.hasher)?;
+        let slot_author = Self::slot_author(slot, properties)
+            .ok_or(BlockVerificationError::NoAuthorities)?;
+        if &seal.author != slot_author {
+            Err(BlockVerificationError::WrongAuthor {
+                slot,
+                expected: slot_author.clone(),
+                found: seal.author.clone(),
+            })?;
         }
 
-        // Verify the block age
-        let block_timestamp = new_block.header.timestamp;
-        let timestamp_difference = current_timestamp() - block_timestamp;
-        let maximum_acceptable_difference = properties.time_tolorant.as_secs();
-        if timestamp_difference > maximum_acceptable_difference {
-            Err("block have aged too much")?;
+        Ok(())
+    }
+}
+
+/// Leader of each slot is drawn from bonded stakers with chance
+/// proportional to their stake. Leader seals block like an authority
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofOfStake;
+
+impl ProofOfStake {
+    pub fn slot_leader(slot: Slot, staking: &StakingStorage, hasher: HashAlgorithm) -> Option<&AccountId> {
+        let total_bonded = staking.total_bonded();
+        if total_bonded == 0 {
+            return None;
         }
 
-        // Verify the work done
-        let difficulty = properties.difficulty;
-        let new_block_hash = new_block.get_hash();
-        if !new_block_hash.difficulty_verified(difficulty) {
-            Err("new block hash do not pass difficulty")?;
+        let slot_seed = Hash::new_under(hasher, [SLOT_LEADER_DOMAIN, &slot.to_le_bytes()].concat());
+        let seed_bytes = slot_seed.as_bytes()[..8]
+            .try_into()
+            .expect("Hash is longer than 8 bytes");
+        let point = u64::from_le_bytes(seed_bytes) % total_bonded;
+
+        staking.staker_at(point)
+    }
+}
+
+impl Consensus for ProofOfStake {
+    fn prepare_block(chain: &mut Chain, new_block: &mut Block) -> ConsensusResult {
+        let latest_block = chain.get_latest_block();
+        new_block.header.parent_block = latest_block.get_hash(chain.properties.hasher);
+        new_block.header.timestamp = next_timestamp(chain, latest_block);
+        new_block.commit_transactions(chain.properties.hasher);
+
+        let slot = slot_at(new_block.header.timestamp, &chain.properties);
+        let slot_leader = Self::slot_leader(slot, &chain.staking, chain.properties.hasher)
+            .ok_or(BlockVerificationError::NoStakers)?;
+        let author_key = chain.author_key
+            .as_ref()
+            .ok_or(ConsensusError::NoAuthorKey)?;
+        if &author_key.public().to_account_id() != slot_leader {
+            Err(ConsensusError::NotLeaderForSlot {
+                slot,
+                leader: slot_leader.clone(),
+            })?;
+        }
+        seal_block(new_block, author_key, chain.properties.hasher);
+
+        Self::verify_new_block(chain, latest_block, new_block)?;
+
+        Ok(())
+    }
+
+    fn verify_block(chain: &Chain, last_block: &Block, new_block: &Block) -> VerificationResult {
+        let properties = &chain.properties;
+        verify_parent_hash(chain, last_block, new_block)?;
+        verify_height(last_block, new_block)?;
+        verify_transactions_root(chain, new_block)?;
+        verify_transaction_signatures(new_block)?;
+        verify_timestamp(chain, last_block, new_block)?;
+        // No work is done so block must not claim any
+        verify_target(new_block, Target::MAX)?;
+        let slot = verify_slot_advanced(last_block, new_block, properties)?;
+
+        // Author must be the leader elected by stake as of parent block. Parent
+        // off the canonical chain has no state yet, so its child's leader is
+        // checked when their branch is replayed on reorg
+        let seal = verify_seal(new_block, properties.hasher)?;
+        let Some(staking) = chain.staking_after(last_block) else {
+            return Ok(());
+        };
+        let slot_leader = Self::slot_leader(slot, staking, properties.hasher)
+            .ok_or(BlockVerificationError::NoStakers)?;
+        let author = seal.author.to_account_id();
+        if &author != slot_leader {
+            Err(BlockVerificationError::WrongLeader {
+                slot,
+                expected: slot_leader.clone(),
+                found: author,
+            })?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::tests::chain_with;
+    use crate::chain::tests::test_chain;
+    use crate::chain::tests::test_properties;
+    use crate::components::difficulty::Work;
+
+    // Child of `parent` known to the tree but never executed, so its timestamp can be anything
+    fn child_at(chain: &mut Chain, parent: &Block, timestamp: u64) -> Block {
+        let hasher = chain.properties.hasher;
+        let mut block = parent.clone();
+        block.header.parent_block = parent.get_hash(hasher);
+        block.header.height += 1;
+        block.header.timestamp = timestamp;
+        chain.block_tree.insert(block.get_hash(hasher), block.clone(), Work::zero());
+        block
+    }
+
+    fn branch_at(chain: &mut Chain, timestamps: &[u64]) -> Block {
+        let genesis = chain.get_genesis_block().clone();
+        timestamps
+            .iter()
+            .fold(genesis, |parent, timestamp| child_at(chain, &parent, *timestamp))
+    }
+
+    #[test]
+    fn median_time_past_ignores_single_skewed_block() {
+        let mut chain = test_chain();
+        let tip = branch_at(&mut chain, &[10, 20, 1_000, 30, 40]);
+
+        // Genesis at 0 is among the six timestamps, so median is the upper middle one
+        assert_eq!(median_time_past(&chain, &tip), 30);
+    }
+
+    #[test]
+    fn timestamp_must_not_precede_parent() {
+        let mut chain = test_chain();
+        let parent = branch_at(&mut chain, &[10, 20, 30]);
+        let child = child_at(&mut chain, &parent, 29);
+
+        assert_eq!(
+            verify_timestamp(&chain, &parent, &child),
+            Err(BlockVerificationError::TimestampBeforeParent { parent_timestamp: 30, timestamp: 29 }),
+        );
+    }
+
+    #[test]
+    fn timestamp_must_be_after_median_time_past() {
+        let mut chain = test_chain();
+        // Parent stamped back in time. Child may share its second but not the median one
+        let parent = branch_at(&mut chain, &[10, 20, 30, 40, 15]);
+        let child = child_at(&mut chain, &parent, 20);
+
+        assert_eq!(
+            verify_timestamp(&chain, &parent, &child),
+            Err(BlockVerificationError::TimestampNotAfterMedianTimePast { median_time_past: 20, timestamp: 20 }),
+        );
+        let child = child_at(&mut chain, &parent, 21);
+        assert_eq!(verify_timestamp(&chain, &parent, &child), Ok(()));
+    }
+
+    #[test]
+    fn fresh_block_may_only_drift_so_far_ahead_of_local_clock() {
+        let chain = test_chain();
+        let properties = &chain.properties;
+        let max_future_drift = properties.max_future_drift.as_secs();
+        let mut block = chain.get_genesis_block().clone();
+
+        // Ahead of clock within drift used to underflow the age check
+        block.header.timestamp = current_timestamp() + max_future_drift / 2;
+        assert_eq!(verify_freshness(&block, properties), Ok(()));
+
+        block.header.timestamp = current_timestamp() + max_future_drift + 60;
+        assert!(matches!(
+            verify_freshness(&block, properties),
+            Err(BlockVerificationError::TimestampTooFarInFuture { .. }),
+        ));
+    }
+
+    #[test]
+    fn fresh_block_may_not_be_older_than_time_tolerance() {
+        let chain = test_chain();
+        let mut block = chain.get_genesis_block().clone();
+        block.header.timestamp = current_timestamp() - chain.properties.time_tolorant.as_secs() - 60;
+
+        assert!(matches!(
+            verify_freshness(&block, &chain.properties),
+            Err(BlockVerificationError::BlockTooOld { .. }),
+        ));
+    }
+
+    // Block on top of `chain` tip stamped with `timestamp` and sealed by `author`
+    fn sealed_at(chain: &Chain, timestamp: u64, author: &Pair) -> Block {
+        let hasher = chain.properties.hasher;
+        let mut block = Block::new(chain);
+        block.header.timestamp = timestamp;
+        block.commit_transactions(hasher);
+        seal_block(&mut block, author, hasher);
+        block
+    }
+
+    fn authority_chain(authorities: &[&Pair]) -> Chain {
+        let properties = ChainProperties {
+            consensus: ConsensusEngine::ProofOfAuthority,
+            authorities: authorities.iter().map(|authority| authority.public()).collect(),
+            ..test_properties()
+        };
+        chain_with(properties, vec![])
+    }
+
+    #[test]
+    fn block_sealed_by_authority_out_of_turn_is_rejected() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let bob = Pair::from_seed(&[2; 32]);
+        let chain = authority_chain(&[&alice, &bob]);
+        let genesis = chain.get_genesis_block();
+        // Slot 2 belongs to first authority
+        let timestamp = 2 * chain.properties.slot_duration.as_secs();
+
+        let block = sealed_at(&chain, timestamp, &bob);
+        assert_eq!(
+            ProofOfAuthority::verify_block(&chain, genesis, &block),
+            Err(BlockVerificationError::WrongAuthor { slot: 2, expected: alice.public(), found: bob.public() }),
+        );
+        let block = sealed_at(&chain, timestamp, &alice);
+        assert_eq!(ProofOfAuthority::verify_block(&chain, genesis, &block), Ok(()));
+    }
+
+    #[test]
+    fn authority_block_in_same_slot_as_parent_is_rejected() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let mut chain = authority_chain(&[&alice]);
+        let slot_duration = chain.properties.slot_duration.as_secs();
+
+        let parent = sealed_at(&chain, 2 * slot_duration, &alice);
+        chain.apply_block(parent.clone()).expect("Empty block executes");
+        let block = sealed_at(&chain, 2 * slot_duration + 1, &alice);
+
+        assert_eq!(
+            ProofOfAuthority::verify_block(&chain, &parent, &block),
+            Err(BlockVerificationError::SlotNotAdvanced { parent_slot: 2, slot: 2 }),
+        );
+    }
+
+    fn stake_chain(stakers: &[&Pair]) -> Chain {
+        let properties = ChainProperties {
+            consensus: ConsensusEngine::ProofOfStake,
+            ..test_properties()
+        };
+        let stakers = stakers
+            .iter()
+            .map(|staker| (staker.public().to_account_id(), 1_000))
+            .collect();
+        chain_with(properties, stakers)
+    }
+
+    #[test]
+    fn block_sealed_by_staker_other_than_leader_is_rejected() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let bob = Pair::from_seed(&[2; 32]);
+        let chain = stake_chain(&[&alice, &bob]);
+        let genesis = chain.get_genesis_block();
+        let alice_id = alice.public().to_account_id();
+        let slot = (1..)
+            .find(|slot| ProofOfStake::slot_leader(*slot, &chain.staking, chain.properties.hasher) == Some(&alice_id))
+            .expect("Half of stake leads about half of slots");
+        let timestamp = slot * chain.properties.slot_duration.as_secs();
+
+        let block = sealed_at(&chain, timestamp, &bob);
+        assert_eq!(
+            ProofOfStake::verify_block(&chain, genesis, &block),
+            Err(BlockVerificationError::WrongLeader { slot, expected: alice_id, found: bob.public().to_account_id() }),
+        );
+        let block = sealed_at(&chain, timestamp, &alice);
+        assert_eq!(ProofOfStake::verify_block(&chain, genesis, &block), Ok(()));
+    }
+
+    #[test]
+    fn staker_block_in_same_slot_as_parent_is_rejected() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let mut chain = stake_chain(&[&alice]);
+        let slot_duration = chain.properties.slot_duration.as_secs();
+
+        let parent = sealed_at(&chain, 2 * slot_duration, &alice);
+        chain.apply_block(parent.clone()).expect("Empty block executes");
+        let block = sealed_at(&chain, 2 * slot_duration + 1, &alice);
+
+        assert_eq!(
+            ProofOfStake::verify_block(&chain, &parent, &block),
+            Err(BlockVerificationError::SlotNotAdvanced { parent_slot: 2, slot: 2 }),
+        );
+    }
+}
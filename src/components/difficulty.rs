@@ -0,0 +1,205 @@
+use primitive_types::U256;
+use primitive_types::U512;
+
+use crate::components::hash::Hash;
+use crate::components::SU_HASHER_LEN;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Expected number of hashes tried to seal a block
+pub type Work = U256;
+
+const MAX_LEADING_ZERO_BITS: u32 = 256;
+/// Hardest target still met by some hash. All 256 zero bits would need
+/// the one all-zero hash
+pub const MAX_DIFFICULTY_BITS: u32 = MAX_LEADING_ZERO_BITS - 1;
+// Fractional bits of fixed point exponent used while retargeting
+const RADIX_BITS: u32 = 16;
+
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+struct PrettyTarget(String);
+
+impl From<Target> for PrettyTarget {
+    fn from(src: Target) -> Self {
+        PrettyTarget(src.to_string())
+    }
+}
+
+impl TryFrom<PrettyTarget> for Target {
+    type Error = String;
+
+    fn try_from(src: PrettyTarget) -> Result<Self, Self::Error> {
+        src.0.parse()
+    }
+}
+
+/// Block hash read as big-endian integer must not exceed the target.
+/// Lower target means more work
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "PrettyTarget")]
+#[serde(try_from = "PrettyTarget")]
+pub struct Target(U256);
+
+/// Accepts `0x` prefixed 256-bit target or number of leading zero bits
+impl core::str::FromStr for Target {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let src = src.trim();
+        if let Some(target_hex) = src.strip_prefix("0x") {
+            let bytes = hex::decode(target_hex)
+                .map_err(|e| format!("Invalid hex value `{src}` for target: {e}"))?;
+            if bytes.len() != SU_HASHER_LEN {
+                Err(format!("Unexpected target length for `{src}`. Expected {SU_HASHER_LEN} bytes, got {}", bytes.len()))?;
+            }
+            let target = U256::from_big_endian(&bytes);
+            if target.is_zero() {
+                Err(format!("Target `{src}` can not be zero. No hash can meet it"))?;
+            }
+            return Ok(Target(target));
+        }
+
+        let bits = src.parse::<u32>()
+            .map_err(|_| format!("Invalid target `{src}`. Expected leading zero bits or 0x prefixed target"))?;
+        if bits > MAX_DIFFICULTY_BITS {
+            Err(format!("Leading zero bits can not exceed {MAX_DIFFICULTY_BITS}"))?;
+        }
+
+        Ok(Target::from_leading_zero_bits(bits))
+    }
+}
+
+impl core::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0.to_big_endian()))
+    }
+}
+
+impl core::fmt::Debug for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Target({self})")
+    }
+}
+
+impl Target {
+    pub const MAX: Target = Target(U256::MAX);
+
+    /// Target met by hashes starting with at least `bits` zero bits.
+    /// Bits beyond `MAX_DIFFICULTY_BITS` are clamped so target never becomes zero
+    pub fn from_leading_zero_bits(bits: u32) -> Self {
+        Target(U256::MAX >> bits.min(MAX_DIFFICULTY_BITS))
+    }
+
+    pub fn to_bytes(&self) -> [u8; SU_HASHER_LEN] {
+        self.0.to_big_endian()
+    }
+
+    pub fn leading_zero_bits(&self) -> u32 {
+        self.0.leading_zeros()
+    }
+
+    pub fn is_met_by(&self, hash: &Hash) -> bool {
+        U256::from_big_endian(hash.as_bytes()) <= self.0
+    }
+
+    /// 2^256 / (target + 1), computed without overflowing 256 bits.
+    /// Zero target would need 2^256 itself so it is capped at `Work::MAX`
+    pub fn work(&self) -> Work {
+        if self.0.is_zero() {
+            return Work::MAX;
+        }
+        if self.0 == U256::MAX {
+            return Work::one();
+        }
+        (!self.0 / (self.0 + 1)) + 1
+    }
+
+    /// ASERT retargeting: target doubles for every `half_life` seconds chain is
+    /// behind schedule and halves for every `half_life` seconds it is ahead.
+    /// `schedule_drift` is actual minus scheduled seconds since the anchor block.
+    /// 2^fraction is approximated with same cubic polynomial as BCH's aserti3-2d
+    pub fn scheduled(&self, schedule_drift: i128, half_life: u64) -> Self {
+        let exponent = (schedule_drift << RADIX_BITS) / i128::from(half_life.max(1));
+        let shifts = exponent >> RADIX_BITS;
+        let fraction = (exponent & ((1 << RADIX_BITS) - 1)) as u128;
+        let factor = (1u128 << RADIX_BITS)
+            + ((195_766_423_245_049 * fraction
+                + 971_821_376 * fraction.pow(2)
+                + 5_127 * fraction.pow(3)
+                + (1 << 47))
+                >> 48);
+
+        let scaled = U512::from(self.0) * U512::from(factor);
+        let shifted = if shifts >= 0 {
+            let shifts = shifts as usize;
+            if scaled.bits().saturating_add(shifts) > (MAX_LEADING_ZERO_BITS + RADIX_BITS) as usize {
+                return Target::MAX;
+            }
+            scaled << shifts
+        } else {
+            scaled >> shifts.unsigned_abs().min(512) as usize
+        };
+
+        // Keep target in [1, MAX] so some hash can always meet it
+        match U256::try_from(shifted >> RADIX_BITS as usize) {
+            Ok(next) if next.is_zero() => Target(U256::one()),
+            Ok(next) => Target(next),
+            Err(_) => Target::MAX,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_LIFE: u64 = 600;
+
+    fn target_at_bit(bit: usize) -> Target {
+        Target(U256::one() << bit)
+    }
+
+    #[test]
+    fn on_schedule_keeps_target() {
+        let target = Target::from_leading_zero_bits(20);
+        assert_eq!(target.scheduled(0, HALF_LIFE), target);
+    }
+
+    #[test]
+    fn half_life_behind_doubles_and_ahead_halves() {
+        let target = Target::from_leading_zero_bits(20);
+        assert_eq!(target.scheduled(HALF_LIFE.into(), HALF_LIFE), Target(target.0 << 1));
+        assert_eq!(target.scheduled(-i128::from(HALF_LIFE), HALF_LIFE), Target(target.0 >> 1));
+        assert_eq!(target.scheduled(3 * i128::from(HALF_LIFE), HALF_LIFE), Target(target.0 << 3));
+    }
+
+    #[test]
+    fn half_of_half_life_scales_by_square_root_of_two() {
+        let scaled = target_at_bit(100).scheduled(i128::from(HALF_LIFE / 2), HALF_LIFE);
+        let factor = (scaled.0 >> 80).as_u64() as f64 / f64::from(1 << 20);
+        assert!((factor - std::f64::consts::SQRT_2).abs() < 1e-3, "factor {factor}");
+
+        let scaled = target_at_bit(100).scheduled(-i128::from(HALF_LIFE / 2), HALF_LIFE);
+        let factor = (scaled.0 >> 80).as_u64() as f64 / f64::from(1 << 20);
+        assert!((factor - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-3, "factor {factor}");
+    }
+
+    #[test]
+    fn scheduled_target_stays_within_bounds() {
+        let target = Target::from_leading_zero_bits(20);
+        assert_eq!(target.scheduled(i128::from(HALF_LIFE) * 300, HALF_LIFE), Target::MAX);
+        assert_eq!(target.scheduled(-i128::from(HALF_LIFE) * 300, HALF_LIFE), Target(U256::one()));
+        assert_eq!(Target::MAX.scheduled(i128::from(HALF_LIFE), HALF_LIFE), Target::MAX);
+    }
+
+    #[test]
+    fn work_is_expected_hashes_at_edges() {
+        assert_eq!(Target::MAX.work(), Work::one());
+        assert_eq!(Target::from_leading_zero_bits(1).work(), Work::from(2));
+        assert_eq!(Target::from_leading_zero_bits(8).work(), Work::from(256));
+        assert_eq!(Target(U256::one()).work(), Work::one() << 255);
+        assert_eq!(Target(U256::zero()).work(), Work::MAX);
+    }
+}
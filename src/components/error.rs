@@ -0,0 +1,351 @@
+use crate::components::hash::Hash;
+use crate::components::AccountId;
+use crate::components::AccountNonce;
+use crate::components::Balance;
+use crate::components::BlockNumber;
+use crate::components::ChainId;
+use crate::components::multisig::MultisigThreshold;
+use crate::components::consensus::Slot;
+use crate::components::difficulty::Target;
+use crate::components::signature::PublicKey;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    MissingSignature,
+    UnexpectedSignature,
+    SignerMismatch {
+        signer: AccountId,
+        origin: AccountId,
+    },
+    InvalidPublicKey,
+    BadSignature,
+}
+
+/// Why a transaction could not be executed against chain state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DispatchError {
+    BadOrigin,
+    Signature(SignatureError),
+    WrongChainId {
+        expected: ChainId,
+        found: ChainId,
+    },
+    StaleNonce {
+        account: AccountId,
+        expected: AccountNonce,
+        found: AccountNonce,
+    },
+    FutureNonce {
+        account: AccountId,
+        expected: AccountNonce,
+        found: AccountNonce,
+    },
+    AccountNotFound(AccountId),
+    InsufficientBalance {
+        account: AccountId,
+        balance: Balance,
+        amount: Balance,
+    },
+    WouldKillAccount(AccountId),
+    BelowExistentialDeposit {
+        account: AccountId,
+        amount: Balance,
+    },
+    SystemAllowanceTooLow,
+    InvalidMultisigThreshold {
+        threshold: MultisigThreshold,
+        signatories: usize,
+    },
+    MultisigExists(AccountId),
+    MultisigNotFound(AccountId),
+    NotSignatory {
+        account: AccountId,
+        multisig: AccountId,
+    },
+    ProposalExists {
+        multisig: AccountId,
+        call_hash: Hash,
+    },
+    ProposalNotFound {
+        multisig: AccountId,
+        call_hash: Hash,
+    },
+    AlreadyApproved {
+        account: AccountId,
+        call_hash: Hash,
+    },
+    NotProposer {
+        account: AccountId,
+        call_hash: Hash,
+    },
+    UnauthenticatedRoot,
+    NoSudoKey,
+    NotSudo(AccountId),
+    InsufficientBonded {
+        account: AccountId,
+        bonded: Balance,
+        amount: Balance,
+    },
+    NothingToWithdraw(AccountId),
+}
+
+/// Why a block can not be placed on top of its parent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockVerificationError {
+    ParentHashMismatch {
+        expected: Hash,
+        found: Hash,
+    },
+    TransactionsRootMismatch {
+        expected: Hash,
+        found: Hash,
+    },
+    HeightMismatch {
+        expected: BlockNumber,
+        found: BlockNumber,
+    },
+    GenesisMismatch {
+        expected: Hash,
+        found: Hash,
+    },
+    TimestampBeforeParent {
+        parent_timestamp: u64,
+        timestamp: u64,
+    },
+    TimestampNotAfterMedianTimePast {
+        median_time_past: u64,
+        timestamp: u64,
+    },
+    TimestampTooFarInFuture {
+        timestamp: u64,
+        max_timestamp: u64,
+    },
+    InvalidSignature {
+        tx_index: usize,
+        error: SignatureError,
+    },
+    BlockTooOld {
+        age: u64,
+        maximum_age: u64,
+    },
+    DifficultyNotMet {
+        target: Target,
+    },
+    TargetMismatch {
+        expected: Target,
+        found: Target,
+    },
+    NoAuthorities,
+    MissingSeal,
+    InvalidSeal(SignatureError),
+    WrongAuthor {
+        slot: Slot,
+        expected: PublicKey,
+        found: PublicKey,
+    },
+    SlotNotAdvanced {
+        parent_slot: Slot,
+        slot: Slot,
+    },
+    NoStakers,
+    WrongLeader {
+        slot: Slot,
+        expected: AccountId,
+        found: AccountId,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConsensusError {
+    Verification(BlockVerificationError),
+    Transaction {
+        tx_index: usize,
+        error: DispatchError,
+    },
+    SealNotFound,
+    MiningCancelled,
+    UnknownParent(Hash),
+    DuplicateBlock(Hash),
+    NoAuthorKey,
+    NotAuthorForSlot {
+        slot: Slot,
+        author: PublicKey,
+    },
+    NotLeaderForSlot {
+        slot: Slot,
+        leader: AccountId,
+    },
+    Genesis(DispatchError),
+}
+
+/// First block that failed while replaying chain from genesis
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainValidationError {
+    pub height: BlockNumber,
+    pub block: Hash,
+    pub error: Box<ConsensusError>,
+}
+
+impl From<SignatureError> for DispatchError {
+    fn from(src: SignatureError) -> Self {
+        DispatchError::Signature(src)
+    }
+}
+
+impl From<BlockVerificationError> for ConsensusError {
+    fn from(src: BlockVerificationError) -> Self {
+        ConsensusError::Verification(src)
+    }
+}
+
+impl core::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::MissingSignature =>
+                write!(f, "signed origin without signature"),
+            SignatureError::UnexpectedSignature =>
+                write!(f, "signature is only allowed with signed origin"),
+            SignatureError::SignerMismatch { signer, origin } =>
+                write!(f, "signer {signer} do not belong to signed origin {origin}"),
+            SignatureError::InvalidPublicKey =>
+                write!(f, "public key is not a valid ed25519 point"),
+            SignatureError::BadSignature =>
+                write!(f, "bad signature"),
+        }
+    }
+}
+
+impl core::fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DispatchError::BadOrigin =>
+                write!(f, "Insufficient permission"),
+            DispatchError::Signature(error) =>
+                write!(f, "{error}"),
+            DispatchError::WrongChainId { expected, found } =>
+                write!(f, "transaction is for chain {found} but this is chain {expected}"),
+            DispatchError::StaleNonce { account, expected, found } =>
+                write!(f, "stale nonce {found}. Account {account} is already at nonce {expected}"),
+            DispatchError::FutureNonce { account, expected, found } =>
+                write!(f, "future nonce {found}. Account {account} expects nonce {expected}"),
+            DispatchError::AccountNotFound(account) =>
+                write!(f, "account {account} doesn't exists"),
+            DispatchError::InsufficientBalance { account, balance, amount } =>
+                write!(f, "balance of {account} too low. Have {balance}, need {amount}"),
+            DispatchError::WouldKillAccount(account) =>
+                write!(f, "can't kill {account}"),
+            DispatchError::BelowExistentialDeposit { account, amount } =>
+                write!(f, "amount {amount} too low to create account {account}"),
+            DispatchError::SystemAllowanceTooLow =>
+                write!(f, "system allowence too low"),
+            DispatchError::InvalidMultisigThreshold { threshold, signatories } =>
+                write!(f, "threshold {threshold} is invalid for {signatories} distinct signatories"),
+            DispatchError::MultisigExists(multisig) =>
+                write!(f, "multisig {multisig} already exists"),
+            DispatchError::MultisigNotFound(multisig) =>
+                write!(f, "multisig {multisig} doesn't exists"),
+            DispatchError::NotSignatory { account, multisig } =>
+                write!(f, "{account} is not a signatory of multisig {multisig}"),
+            DispatchError::ProposalExists { multisig, call_hash } =>
+                write!(f, "call {call_hash} is already proposed for multisig {multisig}"),
+            DispatchError::ProposalNotFound { multisig, call_hash } =>
+                write!(f, "no call {call_hash} is proposed for multisig {multisig}"),
+            DispatchError::AlreadyApproved { account, call_hash } =>
+                write!(f, "{account} already approved call {call_hash}"),
+            DispatchError::NotProposer { account, call_hash } =>
+                write!(f, "{account} did not propose call {call_hash}"),
+            DispatchError::UnauthenticatedRoot =>
+                write!(f, "Root origin can only be obtained through Sudo operation"),
+            DispatchError::NoSudoKey =>
+                write!(f, "chain has no sudo key"),
+            DispatchError::NotSudo(account) =>
+                write!(f, "{account} is not the sudo key"),
+            DispatchError::InsufficientBonded { account, bonded, amount } =>
+                write!(f, "bonded stake of {account} too low. Have {bonded}, need {amount}"),
+            DispatchError::NothingToWithdraw(account) =>
+                write!(f, "{account} has no unbonded stake ready to withdraw"),
+        }
+    }
+}
+
+impl core::fmt::Display for BlockVerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockVerificationError::ParentHashMismatch { expected, found } =>
+                write!(f, "mismatched parent hash in new block header. Expected {expected}, found {found}"),
+            BlockVerificationError::TransactionsRootMismatch { expected, found } =>
+                write!(f, "header does not commit to block transactions. Expected root {expected}, found {found}"),
+            BlockVerificationError::HeightMismatch { expected, found } =>
+                write!(f, "block claims height {found} but height after its parent is {expected}"),
+            BlockVerificationError::GenesisMismatch { expected, found } =>
+                write!(f, "genesis block hashes to {found} but chain was created with genesis {expected}"),
+            BlockVerificationError::TimestampBeforeParent { parent_timestamp, timestamp } =>
+                write!(f, "block timestamp {timestamp} is before its parent's {parent_timestamp}"),
+            BlockVerificationError::TimestampNotAfterMedianTimePast { median_time_past, timestamp } =>
+                write!(f, "block timestamp {timestamp} must be after median time past {median_time_past}"),
+            BlockVerificationError::TimestampTooFarInFuture { timestamp, max_timestamp } =>
+                write!(f, "block timestamp {timestamp} is too far in future. Latest acceptable now is {max_timestamp}"),
+            BlockVerificationError::InvalidSignature { tx_index, error } =>
+                write!(f, "invalid signature in {tx_index}th transaction: {error}"),
+            BlockVerificationError::BlockTooOld { age, maximum_age } =>
+                write!(f, "block have aged too much. Age {age}s, maximum {maximum_age}s"),
+            BlockVerificationError::DifficultyNotMet { target } =>
+                write!(f, "new block hash do not meet target {target}"),
+            BlockVerificationError::TargetMismatch { expected, found } =>
+                write!(f, "block claims target {found} but chain rules expect {expected}"),
+            BlockVerificationError::NoAuthorities =>
+                write!(f, "chain has no authority to seal blocks"),
+            BlockVerificationError::MissingSeal =>
+                write!(f, "block carries no author seal"),
+            BlockVerificationError::InvalidSeal(error) =>
+                write!(f, "invalid author seal: {error}"),
+            BlockVerificationError::WrongAuthor { slot, expected, found } =>
+                write!(f, "slot {slot} belongs to authority {expected} but block is sealed by {found}"),
+            BlockVerificationError::SlotNotAdvanced { parent_slot, slot } =>
+                write!(f, "block slot {slot} is not after parent slot {parent_slot}"),
+            BlockVerificationError::NoStakers =>
+                write!(f, "chain has no bonded stake to elect slot leader"),
+            BlockVerificationError::WrongLeader { slot, expected, found } =>
+                write!(f, "slot {slot} is led by {expected} but block is sealed by {found}"),
+        }
+    }
+}
+
+impl core::fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsensusError::Verification(error) =>
+                write!(f, "Verifying new block: {error}"),
+            ConsensusError::Transaction { tx_index, error } =>
+                write!(f, "Error while performing {tx_index}th transaction: {error}"),
+            ConsensusError::SealNotFound =>
+                write!(f, "Cannot pass difficulty with any value"),
+            ConsensusError::MiningCancelled =>
+                write!(f, "Mining was cancelled before a seal was found"),
+            ConsensusError::UnknownParent(parent) =>
+                write!(f, "Parent block {parent} is not known to this node"),
+            ConsensusError::DuplicateBlock(block) =>
+                write!(f, "Block {block} is already known"),
+            ConsensusError::NoAuthorKey =>
+                write!(f, "No author key set for this node. Use set_author first"),
+            ConsensusError::NotAuthorForSlot { slot, author } =>
+                write!(f, "Slot {slot} belongs to authority {author}. Try again in its slot"),
+            ConsensusError::NotLeaderForSlot { slot, leader } =>
+                write!(f, "Slot {slot} is led by {leader}. Try again in later slot"),
+            ConsensusError::Genesis(error) =>
+                write!(f, "Genesis state can not be built from config: {error}"),
+        }
+    }
+}
+
+impl core::fmt::Display for ChainValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Block #{} {} is invalid. {}", self.height, self.block, self.error)
+    }
+}
+
+impl std::error::Error for SignatureError {}
+impl std::error::Error for DispatchError {}
+impl std::error::Error for BlockVerificationError {}
+impl std::error::Error for ConsensusError {}
+impl std::error::Error for ChainValidationError {}
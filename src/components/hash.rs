@@ -1,39 +1,54 @@
-use sha3::Digest;
-use crate::components::SuHasher;
+use crate::components::hasher::hash_algorithm;
+use crate::components::hasher::HashAlgorithm;
+use crate::components::hasher::SuHasher;
 use crate::components::SU_HASHER_LEN;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+// Leaves and inner nodes of merkle tree are hashed under different
+// prefixes so an inner node can not pass as a leaf
+const MERKLE_LEAF_PREFIX: u8 = 0;
+const MERKLE_NODE_PREFIX: u8 = 1;
+
 #[derive(Serialize, Deserialize)]
 #[serde(transparent)]
 struct PrettyHash(String);
 impl From<Hash> for PrettyHash {
      fn from(src: Hash) -> Self {
         PrettyHash(
-            format!("0x{}", hex::encode(src.0.to_vec()))
+            format!("0x{}", hex::encode(src.0))
         )
      }
 }
 
-impl From<PrettyHash> for Hash {
-    fn from(src: PrettyHash) -> Self {
-        let hash_string = {
-            if src.0.starts_with("0x") {
-                &src.0[2..]
-            } else {
-                &src.0[..]
-            }
-        };
+impl TryFrom<PrettyHash> for Hash {
+    type Error = String;
 
-        let bytes = hex::decode(hash_string).expect("Invalid hex value");
-        Hash(bytes.try_into().expect("Unexpected hash length"))
+    fn try_from(src: PrettyHash) -> Result<Self, Self::Error> {
+        src.0.parse()
+    }
+}
+
+impl core::str::FromStr for Hash {
+    type Err = String;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        let hash_string = src.strip_prefix("0x").unwrap_or(src);
+
+        let bytes = hex::decode(hash_string)
+            .map_err(|e| format!("Invalid hex value `{src}` for hash: {e}"))?;
+        let bytes_len = bytes.len();
+        let hash_bytes = bytes.try_into()
+            .map_err(|_| format!("Unexpected hash length for `{src}`. Expected {SU_HASHER_LEN} bytes, got {bytes_len}"))?;
+
+        Ok(Hash(hash_bytes))
     }
 }
 
 #[derive(Clone, PartialEq, Eq, Serialize, Hash, Deserialize)]
 #[serde(into = "PrettyHash")]
-#[serde(from = "PrettyHash")]
+#[serde(try_from = "PrettyHash")]
 pub struct Hash([u8; SU_HASHER_LEN]);
 
 impl core::fmt::Display for Hash {
@@ -44,23 +59,109 @@ impl core::fmt::Display for Hash {
     }
 }
 
+impl core::fmt::Debug for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Hash(0x{})", hex::encode(self.0))
+    }
+}
+
 impl Hash {
     pub fn raw(hash_value: [u8; SU_HASHER_LEN]) -> Self {
         Hash(hash_value)
     }
 
+    pub fn as_bytes(&self) -> &[u8; SU_HASHER_LEN] {
+        &self.0
+    }
+
+    /// Hash under the algorithm selected by the running chain
     pub fn new(message: impl AsRef<[u8]>) -> Self {
-        let mut hasher = SuHasher::new();
-        hasher.update(message);
-        let output = hasher.finalize()[..]
-            .to_vec()
-            .try_into()
-            .expect(&format!("SuHasher's output must be reprsentable as [u8; {SU_HASHER_LEN}]"));
+        Self::new_under(hash_algorithm(), message)
+    }
 
-        Hash(output)
+    /// Hash under `hasher` whatever the running chain uses
+    pub fn new_under(hasher: HashAlgorithm, message: impl AsRef<[u8]>) -> Self {
+        Hash(hasher.digest(message.as_ref()))
     }
 
-    pub fn difficulty_verified(&self, difficulty_level: usize) -> bool {
-        self.0.starts_with(&vec![0u8; difficulty_level])
+    /// Hash under `H` whatever the running chain uses
+    pub fn new_with<H: SuHasher>(message: impl AsRef<[u8]>) -> Self {
+        Hash(H::digest(message.as_ref()))
+    }
+
+    /// Root of binary merkle tree over `leaves`. Odd node is carried up a
+    /// level as is instead of being paired with itself. No leaves give all-zero root
+    pub fn merkle_root(hasher: HashAlgorithm, leaves: impl IntoIterator<Item = Hash>) -> Self {
+        let mut level = leaves
+            .into_iter()
+            .map(|leaf| Hash::new_under(hasher, [[MERKLE_LEAF_PREFIX].as_slice(), &leaf.0].concat()))
+            .collect::<Vec<_>>();
+        if level.is_empty() {
+            return Hash([0u8; SU_HASHER_LEN]);
+        }
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|nodes| match nodes {
+                    [left, right] => Hash::new_under(hasher, [[MERKLE_NODE_PREFIX].as_slice(), &left.0, &right.0].concat()),
+                    [odd] => odd.clone(),
+                    _ => unreachable!("chunks(2) yields one or two nodes"),
+                })
+                .collect();
+        }
+
+        level.swap_remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASHER: HashAlgorithm = HashAlgorithm::Sha3_256;
+
+    fn leaves(count: u8) -> Vec<Hash> {
+        (0..count).map(|index| Hash::new_under(HASHER, [index])).collect()
+    }
+
+    fn leaf(hash: &Hash) -> Hash {
+        Hash::new_under(HASHER, [[MERKLE_LEAF_PREFIX].as_slice(), &hash.0].concat())
+    }
+
+    fn node(left: &Hash, right: &Hash) -> Hash {
+        Hash::new_under(HASHER, [[MERKLE_NODE_PREFIX].as_slice(), &left.0, &right.0].concat())
+    }
+
+    #[test]
+    fn empty_and_single_leaf_roots() {
+        assert_eq!(Hash::merkle_root(HASHER, []), Hash([0; SU_HASHER_LEN]));
+
+        let single = leaves(1);
+        assert_eq!(Hash::merkle_root(HASHER, single.clone()), leaf(&single[0]));
+    }
+
+    #[test]
+    fn odd_node_is_carried_up_unpaired() {
+        let three = leaves(3);
+        let [a, b, c] = [&three[0], &three[1], &three[2]].map(leaf);
+        assert_eq!(Hash::merkle_root(HASHER, three), node(&node(&a, &b), &c));
+
+        let five = leaves(5);
+        let [a, b, c, d, e] = [&five[0], &five[1], &five[2], &five[3], &five[4]].map(leaf);
+        assert_eq!(Hash::merkle_root(HASHER, five), node(&node(&node(&a, &b), &node(&c, &d)), &e));
+    }
+
+    #[test]
+    fn root_commits_to_order_and_level() {
+        let mut two = leaves(2);
+        let root = Hash::merkle_root(HASHER, two.clone());
+        two.reverse();
+        assert_ne!(Hash::merkle_root(HASHER, two), root);
+
+        // Inner node passed off as a leaf does not give the same root
+        let inner = node(&leaf(&leaves(2)[0]), &leaf(&leaves(2)[1]));
+        assert_eq!(inner, root);
+        assert_ne!(Hash::merkle_root(HASHER, [inner]), root);
     }
 }
@@ -0,0 +1,113 @@
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+use blake2::digest::consts::U32;
+use blake2::Blake2b;
+use sha3::Digest;
+use crate::components::SU_HASHER_LEN;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Hash function behind `Hash::new`. Output is always `SU_HASHER_LEN` bytes
+pub trait SuHasher {
+    fn digest(message: &[u8]) -> [u8; SU_HASHER_LEN];
+}
+
+pub struct Sha3_256;
+impl SuHasher for Sha3_256 {
+    fn digest(message: &[u8]) -> [u8; SU_HASHER_LEN] {
+        sha3::Sha3_256::digest(message).into()
+    }
+}
+
+pub struct Blake2b256;
+impl SuHasher for Blake2b256 {
+    fn digest(message: &[u8]) -> [u8; SU_HASHER_LEN] {
+        Blake2b::<U32>::digest(message).into()
+    }
+}
+
+pub struct Keccak256;
+impl SuHasher for Keccak256 {
+    fn digest(message: &[u8]) -> [u8; SU_HASHER_LEN] {
+        sha3::Keccak256::digest(message).into()
+    }
+}
+
+/// sha256(sha256(message)) the way bitcoin hashes
+pub struct DoubleSha256;
+impl SuHasher for DoubleSha256 {
+    fn digest(message: &[u8]) -> [u8; SU_HASHER_LEN] {
+        sha2::Sha256::digest(sha2::Sha256::digest(message)).into()
+    }
+}
+
+/// Hash function chosen per chain in `ChainProperties`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    #[default]
+    Sha3_256,
+    Blake2b256,
+    Keccak256,
+    DoubleSha256,
+}
+
+impl HashAlgorithm {
+    pub fn digest(self, message: &[u8]) -> [u8; SU_HASHER_LEN] {
+        match self {
+            HashAlgorithm::Sha3_256 => Sha3_256::digest(message),
+            HashAlgorithm::Blake2b256 => Blake2b256::digest(message),
+            HashAlgorithm::Keccak256 => Keccak256::digest(message),
+            HashAlgorithm::DoubleSha256 => DoubleSha256::digest(message),
+        }
+    }
+}
+
+impl TryFrom<u8> for HashAlgorithm {
+    type Error = String;
+
+    fn try_from(src: u8) -> Result<Self, Self::Error> {
+        [
+            HashAlgorithm::Sha3_256,
+            HashAlgorithm::Blake2b256,
+            HashAlgorithm::Keccak256,
+            HashAlgorithm::DoubleSha256,
+        ]
+            .into_iter()
+            .find(|algorithm| *algorithm as u8 == src)
+            .ok_or_else(|| format!("Unknown hash algorithm {src}"))
+    }
+}
+
+// Algorithm used by `Hash::new`. Set by the running chain the same way
+// as the address prefix
+static HASH_ALGORITHM: AtomicU8 = AtomicU8::new(HashAlgorithm::Sha3_256 as u8);
+
+pub fn set_hash_algorithm(algorithm: HashAlgorithm) {
+    HASH_ALGORITHM.store(algorithm as u8, Ordering::Relaxed);
+}
+
+pub fn hash_algorithm() -> HashAlgorithm {
+    HashAlgorithm::try_from(HASH_ALGORITHM.load(Ordering::Relaxed))
+        .expect("Only ever stored from a HashAlgorithm")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithm_round_trips_through_u8() {
+        let algorithms = [
+            HashAlgorithm::Sha3_256,
+            HashAlgorithm::Blake2b256,
+            HashAlgorithm::Keccak256,
+            HashAlgorithm::DoubleSha256,
+        ];
+        for algorithm in algorithms {
+            assert_eq!(HashAlgorithm::try_from(algorithm as u8), Ok(algorithm));
+        }
+        assert!(HashAlgorithm::try_from(algorithms.len() as u8).is_err());
+    }
+}
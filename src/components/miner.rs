@@ -0,0 +1,189 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::components::block::Block;
+use crate::components::block::BlockHeader;
+use crate::components::current_timestamp;
+use crate::components::difficulty::Target;
+use crate::components::error::ConsensusError;
+use crate::components::hasher::HashAlgorithm;
+use crate::components::ExtraNonce;
+use crate::components::Nonce;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MiningReport {
+    pub hashes: u64,
+    pub elapsed: Duration,
+    pub threads: usize,
+}
+
+impl MiningReport {
+    pub fn hash_rate(&self) -> f64 {
+        self.hashes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl core::fmt::Display for MiningReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} hashes in {:.2}s on {} thread(s), {:.0} H/s",
+            self.hashes,
+            self.elapsed.as_secs_f64(),
+            self.threads,
+            self.hash_rate(),
+        )
+    }
+}
+
+/// Searches seal for PoW blocks on several threads. Clones share the
+/// cancel generation so a job can be stopped from outside, e.g when a new tip arrives
+#[derive(Clone)]
+pub struct Miner {
+    threads: usize,
+    generation: Arc<AtomicU64>,
+    last_report: Arc<Mutex<Option<MiningReport>>>,
+}
+
+/// Cancel generation a job was created under. Taken together with the
+/// template so a cancel landing before search starts still stops it
+#[derive(Clone, Copy)]
+pub struct MiningTicket(u64);
+
+impl Default for Miner {
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+        Miner::new(threads)
+    }
+}
+
+impl Miner {
+    pub fn new(threads: usize) -> Self {
+        Miner {
+            threads: threads.max(1),
+            generation: Arc::new(AtomicU64::new(0)),
+            last_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Stop every job ticketed so far. Every worker returns after its current hash
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn ticket(&self) -> MiningTicket {
+        MiningTicket(self.generation.load(Ordering::Relaxed))
+    }
+
+    fn is_cancelled(&self, ticket: MiningTicket) -> bool {
+        self.generation.load(Ordering::Relaxed) != ticket.0
+    }
+
+    pub fn last_report(&self) -> Option<MiningReport> {
+        *self.last_report.lock().expect("Mining report lock poisoned")
+    }
+
+    /// Search nonce and extra nonce until header hash meets target. Nonce
+    /// space is strided across worker threads. Only header is hashed, so
+    /// transactions must already be committed. On success `block` carries
+    /// the timestamp, nonce and extra nonce that sealed it
+    pub fn mine(&self, block: &mut Block, target: Target, hasher: HashAlgorithm, ticket: MiningTicket) -> Result<MiningReport, ConsensusError> {
+        let found = AtomicBool::new(false);
+        let hashes = AtomicU64::new(0);
+        let started = Instant::now();
+
+        let sealed = std::thread::scope(|scope| {
+            let workers = (0..self.threads)
+                .map(|worker| {
+                    let job = MiningJob {
+                        header: block.header.clone(),
+                        target,
+                        hasher,
+                        worker,
+                        threads: self.threads,
+                    };
+                    let found = &found;
+                    let hashes = &hashes;
+                    let cancelled = || self.is_cancelled(ticket);
+                    scope.spawn(move || job.run(found, cancelled, hashes))
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .filter_map(|worker| worker.join().expect("Mining worker panicked"))
+                .next()
+        });
+
+        let report = MiningReport {
+            hashes: hashes.into_inner(),
+            elapsed: started.elapsed(),
+            threads: self.threads,
+        };
+        *self.last_report.lock().expect("Mining report lock poisoned") = Some(report);
+
+        match sealed {
+            Some(sealed) => {
+                block.header = sealed;
+                Ok(report)
+            }
+            None if self.is_cancelled(ticket) => Err(ConsensusError::MiningCancelled),
+            None => Err(ConsensusError::SealNotFound),
+        }
+    }
+}
+
+struct MiningJob {
+    header: BlockHeader,
+    target: Target,
+    hasher: HashAlgorithm,
+    worker: usize,
+    threads: usize,
+}
+
+impl MiningJob {
+    fn run(mut self, found: &AtomicBool, cancelled: impl Fn() -> bool, hashes: &AtomicU64) -> Option<BlockHeader> {
+        let mut tried = 0u64;
+        let first_nonce = Nonce::try_from(self.worker).unwrap_or(Nonce::MAX);
+        let earliest_timestamp = self.header.timestamp;
+
+        for extra_nonce in 0..=ExtraNonce::MAX {
+            self.header.extra_nonce = extra_nonce;
+            // Every round of nonces also brings timestamp up to date
+            self.header.timestamp = current_timestamp().max(earliest_timestamp);
+
+            for nonce in (first_nonce..=Nonce::MAX).step_by(self.threads) {
+                if found.load(Ordering::Relaxed) || cancelled() {
+                    hashes.fetch_add(tried, Ordering::Relaxed);
+                    return None;
+                }
+
+                self.header.nonce = nonce;
+                tried += 1;
+                if self.target.is_met_by(&self.header.get_hash(self.hasher)) {
+                    found.store(true, Ordering::Relaxed);
+                    hashes.fetch_add(tried, Ordering::Relaxed);
+                    return Some(self.header);
+                }
+            }
+        }
+
+        hashes.fetch_add(tried, Ordering::Relaxed);
+        None
+    }
+}
@@ -1,14 +1,26 @@
+pub mod account;
 pub mod block;
+pub mod block_tree;
 pub mod hash;
+pub mod hasher;
 pub mod transaction;
 pub mod origin;
 pub mod consensus;
+pub mod difficulty;
+pub mod error;
+pub mod miner;
+pub mod multisig;
+pub mod signature;
+pub mod staking;
 
 pub type Nonce = u32;
+pub type ExtraNonce = u64;
 pub type BlockNumber = u64;
-pub type AccountId = hash::Hash;
+pub type AccountId = account::AccountId;
+pub type AddressPrefix = u16;
 pub type Balance = u64;
-pub type SuHasher = sha3::Sha3_256;
+pub type AccountNonce = u64;
+pub type ChainId = u64;
 pub const SU_HASHER_LEN: usize = 32_usize;
 
 pub fn current_timestamp() -> u64 {
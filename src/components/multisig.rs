@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::components::hash::Hash;
+use crate::components::transaction::Operation;
+use crate::components::AccountId;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+pub type MultisigThreshold = u16;
+pub type MappedMultisigInfo = HashMap<AccountId, MultisigInfo>;
+/// Pending proposals of each multisig account keyed by hash of proposed call
+pub type MappedProposals = HashMap<AccountId, HashMap<Hash, MultisigProposal>>;
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigInfo {
+    pub signatories: Vec<AccountId>,
+    pub threshold: MultisigThreshold,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultisigProposal {
+    pub proposer: AccountId,
+    pub call: Operation,
+    pub approvals: Vec<AccountId>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MultisigStorage {
+    pub accounts: MappedMultisigInfo,
+    pub proposals: MappedProposals,
+}
+
+impl MultisigInfo {
+    /// Signatories are sorted and deduplicated so same set always gives same account
+    pub fn new(mut signatories: Vec<AccountId>, threshold: MultisigThreshold) -> Self {
+        signatories.sort();
+        signatories.dedup();
+        MultisigInfo {
+            signatories,
+            threshold,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.threshold >= 1 && usize::from(self.threshold) <= self.signatories.len()
+    }
+
+    // Raw signatory ids rather than their addresses, so account does not
+    // change with address prefix of the chain
+    pub fn account_id(&self) -> AccountId {
+        let mut preimage = b"multisig:".to_vec();
+        for signatory in &self.signatories {
+            preimage.extend_from_slice(signatory.as_bytes());
+        }
+        preimage.extend_from_slice(&self.threshold.to_le_bytes());
+
+        Hash::new(preimage).into()
+    }
+}
+
+pub fn call_hash(call: &Operation) -> Hash {
+    let call_as_json = serde_json::to_string(call)
+        .expect("Cannot represent Operation{} as json string");
+
+    Hash::new(call_as_json.as_bytes())
+}
@@ -0,0 +1,215 @@
+use ed25519_dalek::Signer;
+use ed25519_dalek::Verifier;
+
+use crate::components::hash::Hash;
+use crate::components::hasher::Sha3_256;
+use crate::components::AccountId;
+use crate::components::error::SignatureError;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+pub const PUBLIC_KEY_LEN: usize = ed25519_dalek::PUBLIC_KEY_LENGTH;
+pub const SIGNATURE_LEN: usize = ed25519_dalek::SIGNATURE_LENGTH;
+pub const SEED_LEN: usize = ed25519_dalek::SECRET_KEY_LENGTH;
+
+/// Well known phrase used for development accounts like `//Alice`. Never fund it on real chain
+pub const DEV_PHRASE: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+const DERIVATION_DOMAIN: &[u8] = b"SuchainHDKD";
+const MNEMONIC_ENTROPY_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+struct PrettyBytes(String);
+
+impl PrettyBytes {
+    fn encode(bytes: &[u8]) -> Self {
+        PrettyBytes(format!("0x{}", hex::encode(bytes)))
+    }
+
+    fn decode<const N: usize>(&self, what: &str) -> Result<[u8; N], String> {
+        let hex_string = self.0.strip_prefix("0x").unwrap_or(&self.0);
+        let bytes = hex::decode(hex_string)
+            .map_err(|e| format!("Invalid hex value for {what}: {e}"))?;
+        let bytes_len = bytes.len();
+        bytes.try_into()
+            .map_err(|_| format!("Expected {N} bytes for {what}, got {bytes_len}"))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "PrettyBytes")]
+#[serde(try_from = "PrettyBytes")]
+pub struct PublicKey([u8; PUBLIC_KEY_LEN]);
+
+impl From<PublicKey> for PrettyBytes {
+    fn from(src: PublicKey) -> Self {
+        PrettyBytes::encode(&src.0)
+    }
+}
+
+impl TryFrom<PrettyBytes> for PublicKey {
+    type Error = String;
+
+    fn try_from(src: PrettyBytes) -> Result<Self, Self::Error> {
+        src.decode("public key").map(PublicKey)
+    }
+}
+
+impl core::fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x{}", hex::encode(self.0))
+    }
+}
+
+impl core::fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PublicKey({self})")
+    }
+}
+
+impl PublicKey {
+    pub fn raw(bytes: [u8; PUBLIC_KEY_LEN]) -> Self {
+        PublicKey(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LEN] {
+        &self.0
+    }
+
+    /// Account controlled by this key. Same as hashing the raw public key bytes
+    pub fn to_account_id(&self) -> AccountId {
+        Hash::new(self.0).into()
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        let verifying_key = ed25519_dalek::VerifyingKey::from_bytes(&self.0)
+            .map_err(|_| SignatureError::InvalidPublicKey)?;
+        let signature = ed25519_dalek::Signature::from_bytes(&signature.0);
+
+        verifying_key.verify(message, &signature)
+            .map_err(|_| SignatureError::BadSignature)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "PrettyBytes")]
+#[serde(try_from = "PrettyBytes")]
+pub struct Signature([u8; SIGNATURE_LEN]);
+
+impl From<Signature> for PrettyBytes {
+    fn from(src: Signature) -> Self {
+        PrettyBytes::encode(&src.0)
+    }
+}
+
+impl TryFrom<PrettyBytes> for Signature {
+    type Error = String;
+
+    fn try_from(src: PrettyBytes) -> Result<Self, Self::Error> {
+        src.decode("signature").map(Signature)
+    }
+}
+
+impl Signature {
+    pub fn as_bytes(&self) -> &[u8; SIGNATURE_LEN] {
+        &self.0
+    }
+}
+
+/// A signing keypair. Never serialized, the secret only leaves through `seed()`
+#[derive(Clone)]
+pub struct Pair(ed25519_dalek::SigningKey);
+
+impl Pair {
+    pub fn generate() -> Self {
+        Pair(ed25519_dalek::SigningKey::generate(&mut rand::rngs::OsRng))
+    }
+
+    pub fn from_seed(seed: &[u8; SEED_LEN]) -> Self {
+        Pair(ed25519_dalek::SigningKey::from_bytes(seed))
+    }
+
+    /// Fresh 12 words BIP39 mnemonic
+    pub fn generate_phrase() -> String {
+        let mut entropy = [0u8; MNEMONIC_ENTROPY_LEN];
+        rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut entropy);
+        bip39::Mnemonic::from_entropy(&entropy)
+            .expect("16 bytes is valid BIP39 entropy length")
+            .to_string()
+    }
+
+    /// Master key of a BIP39 mnemonic. First half of BIP39 seed is used as ed25519 seed
+    pub fn from_phrase(phrase: &str, password: &str) -> Result<Self, String> {
+        let mnemonic = bip39::Mnemonic::parse_normalized(phrase)
+            .map_err(|e| format!("Invalid mnemonic phrase: {e}"))?;
+        let bip39_seed = mnemonic.to_seed_normalized(password);
+        let seed: [u8; SEED_LEN] = bip39_seed[..SEED_LEN]
+            .try_into()
+            .expect("BIP39 seed is longer than ed25519 seed");
+
+        Ok(Pair::from_seed(&seed))
+    }
+
+    /// Parse secret uri of form `<secret>//<junction>//<junction>..`.
+    /// Secret can be a mnemonic phrase, a `0x` hex seed or empty for `DEV_PHRASE`.
+    /// e.g. `//Alice`, `//Bob//stash`, `<12 words>//wallet//0`
+    pub fn from_suri(suri: &str) -> Result<Self, String> {
+        let suri = suri.trim();
+        let (secret, path) = match suri.find("//") {
+            Some(path_start) => suri.split_at(path_start),
+            None => (suri, ""),
+        };
+        let secret = secret.trim();
+
+        let master = if secret.is_empty() {
+            Pair::from_phrase(DEV_PHRASE, "")?
+        } else if let Some(hex_seed) = secret.strip_prefix("0x") {
+            let seed = PrettyBytes(hex_seed.to_string()).decode("seed")?;
+            Pair::from_seed(&seed)
+        } else {
+            Pair::from_phrase(secret, "")?
+        };
+
+        master.derive(path)
+    }
+
+    /// Derive child key by path of hard junctions e.g. `//Bob//stash`.
+    /// ed25519 have no public derivation so soft junctions (`/name`) are rejected
+    pub fn derive(&self, path: &str) -> Result<Self, String> {
+        if path.is_empty() {
+            return Ok(self.clone());
+        }
+        let junctions = path
+            .strip_prefix("//")
+            .ok_or_else(|| format!("Derivation path must start with `//`, got `{path}`"))?;
+
+        let mut derived = self.clone();
+        for junction in junctions.split("//") {
+            if junction.is_empty() || junction.contains('/') {
+                Err(format!("Invalid junction `{junction}`. Only hard junctions (`//name`) are supported"))?;
+            }
+            derived = derived.derive_hard(junction);
+        }
+
+        Ok(derived)
+    }
+
+    fn derive_hard(&self, junction: &str) -> Self {
+        let derivation_input = [DERIVATION_DOMAIN, &self.seed(), junction.as_bytes()].concat();
+        // Fixed so a key derived from the same phrase is the same on every chain
+        Pair::from_seed(Hash::new_with::<Sha3_256>(derivation_input).as_bytes())
+    }
+
+    pub fn seed(&self) -> [u8; SEED_LEN] {
+        self.0.to_bytes()
+    }
+
+    pub fn public(&self) -> PublicKey {
+        PublicKey(self.0.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        Signature(self.0.sign(message).to_bytes())
+    }
+}
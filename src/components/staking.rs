@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use crate::components::AccountId;
+use crate::components::Balance;
+use crate::components::BlockNumber;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Kept ordered so every node walks stakers in same order while electing slot leader
+pub type MappedStakingLedger = BTreeMap<AccountId, StakingLedger>;
+
+/// Unbonded amount that can be withdrawn once chain reaches `unlock_at`
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnlockChunk {
+    pub amount: Balance,
+    pub unlock_at: BlockNumber,
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakingLedger {
+    pub bonded: Balance,
+    pub unlocking: Vec<UnlockChunk>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct StakingStorage {
+    pub ledgers: MappedStakingLedger,
+}
+
+impl StakingLedger {
+    pub fn is_empty(&self) -> bool {
+        self.bonded == 0 && self.unlocking.is_empty()
+    }
+
+    /// Remove chunks unlocked at given height and return their total
+    pub fn take_unlocked(&mut self, height: BlockNumber) -> Balance {
+        let (unlocked, still_locked) = std::mem::take(&mut self.unlocking)
+            .into_iter()
+            .partition::<Vec<_>, _>(|chunk| chunk.unlock_at <= height);
+        self.unlocking = still_locked;
+
+        unlocked.iter().map(|chunk| chunk.amount).sum()
+    }
+}
+
+impl StakingStorage {
+    pub fn total_bonded(&self) -> Balance {
+        self.ledgers.values().map(|ledger| ledger.bonded).sum()
+    }
+
+    /// Pick staker whose bonded range contains `point`. Point must be below `total_bonded()`
+    pub fn staker_at(&self, mut point: Balance) -> Option<&AccountId> {
+        for (staker, ledger) in &self.ledgers {
+            if point < ledger.bonded {
+                return Some(staker);
+            }
+            point -= ledger.bonded;
+        }
+
+        None
+    }
+}
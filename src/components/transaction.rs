@@ -1,14 +1,22 @@
-use std::borrow::Cow;
-
 use crate::components::AccountId;
+use crate::components::error::DispatchError;
+use crate::components::error::SignatureError;
+use crate::components::AccountNonce;
+use crate::components::ChainId;
 use crate::components::origin::Origin;
 use crate::components::Balance;
+use crate::components::hash::Hash;
+use crate::components::hasher::HashAlgorithm;
+use crate::components::multisig::MultisigThreshold;
+use crate::components::signature::Pair;
+use crate::components::signature::PublicKey;
+use crate::components::signature::Signature;
 
 use serde::Serialize;
 use serde::Deserialize;
 
 pub type TransactionCollection = Vec<Transaction>;
-pub type TransactionResult = Result<(), Cow<'static, str>>;
+pub type TransactionResult = Result<(), DispatchError>;
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Operation {
@@ -25,13 +33,64 @@ pub enum Operation {
     Airdrop {
         receiver: AccountId,
         amount: Balance,
-    }
+    },
+    CreateMultisig {
+        signatories: Vec<AccountId>,
+        threshold: MultisigThreshold,
+    },
+    ProposeMultisig {
+        multisig: AccountId,
+        call: Box<Operation>,
+    },
+    ApproveMultisig {
+        multisig: AccountId,
+        call_hash: Hash,
+    },
+    CancelMultisig {
+        multisig: AccountId,
+        call_hash: Hash,
+    },
+    Sudo {
+        call: Box<Operation>,
+    },
+    SetSudoKey {
+        new_key: AccountId,
+    },
+    RemoveSudo,
+    Bond {
+        amount: Balance,
+    },
+    Unbond {
+        amount: Balance,
+    },
+    WithdrawUnbonded,
 }
 
 #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
     pub operation: Operation,
     pub initiator: Origin,
+    #[serde(default)]
+    pub nonce: AccountNonce,
+    #[serde(default)]
+    pub chain_id: ChainId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<TransactionSignature>,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionSignature {
+    pub signer: PublicKey,
+    pub signature: Signature,
+}
+
+/// Part of transaction that is covered by the signature
+#[derive(Serialize)]
+struct SigningPayload<'tx> {
+    operation: &'tx Operation,
+    initiator: &'tx Origin,
+    nonce: AccountNonce,
+    chain_id: ChainId,
 }
 
 impl core::fmt::Debug for Transaction {
@@ -42,18 +101,154 @@ impl core::fmt::Debug for Transaction {
     }
 }
 
+impl Transaction {
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let payload = SigningPayload {
+            operation: &self.operation,
+            initiator: &self.initiator,
+            nonce: self.nonce,
+            chain_id: self.chain_id,
+        };
+        serde_json::to_vec(&payload)
+            .expect("Cannot represent Transaction{} as json string")
+    }
+
+    pub fn get_hash(&self, hasher: HashAlgorithm) -> Hash {
+        let tx_as_json = serde_json::to_string(self)
+            .expect("Cannot represent Transaction{} as json string");
+
+        Hash::new_under(hasher, tx_as_json.as_bytes())
+    }
+
+    pub fn sign(mut self, pair: &Pair) -> Self {
+        let signature = pair.sign(&self.signing_payload());
+        self.signature = Some(TransactionSignature {
+            signer: pair.public(),
+            signature,
+        });
+        self
+    }
+
+    pub fn verify_signature(&self) -> Result<(), SignatureError> {
+        match (&self.initiator, &self.signature) {
+            (Origin::Signed(account_id), Some(tx_signature)) => {
+                let signer = tx_signature.signer.to_account_id();
+                if &signer != account_id {
+                    Err(SignatureError::SignerMismatch {
+                        signer,
+                        origin: account_id.clone(),
+                    })?;
+                }
+                tx_signature.signer
+                    .verify(&self.signing_payload(), &tx_signature.signature)
+            }
+            (Origin::Signed(_), None) =>
+                Err(SignatureError::MissingSignature),
+            (_, Some(_)) =>
+                Err(SignatureError::UnexpectedSignature),
+            (_, None) =>
+                Ok(()),
+        }
+    }
+}
 
 impl Operation {
     pub fn is_privilaged(&self, origin: &Origin) -> bool {
         match self {
-            Operation::Empty | Operation::Panic =>
-                true,
+            Operation::Empty =>
+                origin.unsigned().is_none(),
+            Operation::Panic =>
+                origin.root().is_some(),
             Operation::DestroyAccount { account_id } =>
                 origin.signed() == Some(account_id),
             Operation::TransferFund { sender, .. } =>
                 origin.signed() == Some(sender),
             Operation::Airdrop { receiver, .. } =>
-                origin.signed() == Some(receiver)
+                origin.signed() == Some(receiver),
+            // Membership and proposer are checked while dispatching
+            Operation::CreateMultisig { .. }
+                | Operation::ProposeMultisig { .. }
+                | Operation::ApproveMultisig { .. }
+                | Operation::CancelMultisig { .. } =>
+                origin.signed().is_some(),
+            // Sudo key is checked while dispatching
+            Operation::Sudo { .. }
+                | Operation::SetSudoKey { .. }
+                | Operation::RemoveSudo =>
+                origin.signed().is_some(),
+            Operation::Bond { .. }
+                | Operation::Unbond { .. }
+                | Operation::WithdrawUnbonded =>
+                origin.signed().is_some(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_by(pair: &Pair, operation: Operation) -> Transaction {
+        Transaction {
+            operation,
+            initiator: Origin::Signed(pair.public().to_account_id()),
+            nonce: 0,
+            chain_id: 1,
+            signature: None,
+        }
+        .sign(pair)
+    }
+
+    #[test]
+    fn signature_covers_payload() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let transaction = signed_by(&alice, Operation::Empty);
+        assert_eq!(transaction.verify_signature(), Ok(()));
+
+        let mut tampered = transaction.clone();
+        tampered.nonce += 1;
+        assert_eq!(tampered.verify_signature(), Err(SignatureError::BadSignature));
+
+        let mut tampered = transaction;
+        tampered.operation = Operation::Panic;
+        assert_eq!(tampered.verify_signature(), Err(SignatureError::BadSignature));
+    }
+
+    #[test]
+    fn signer_must_be_the_signed_origin() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let bob = Pair::from_seed(&[2; 32]);
+        let mut transaction = signed_by(&bob, Operation::Empty);
+        transaction.initiator = Origin::Signed(alice.public().to_account_id());
+
+        assert_eq!(
+            transaction.verify_signature(),
+            Err(SignatureError::SignerMismatch {
+                signer: bob.public().to_account_id(),
+                origin: alice.public().to_account_id(),
+            }),
+        );
+    }
+
+    #[test]
+    fn signed_origin_requires_signature() {
+        let alice = Pair::from_seed(&[1; 32]);
+        let mut transaction = signed_by(&alice, Operation::Empty);
+        transaction.signature = None;
+
+        assert_eq!(transaction.verify_signature(), Err(SignatureError::MissingSignature));
+    }
+
+    #[test]
+    fn only_signed_origin_may_carry_signature() {
+        let alice = Pair::from_seed(&[1; 32]);
+        for initiator in [Origin::Root, Origin::Unsigned] {
+            let mut transaction = signed_by(&alice, Operation::Empty);
+            transaction.initiator = initiator;
+            assert_eq!(transaction.verify_signature(), Err(SignatureError::UnexpectedSignature));
+
+            transaction.signature = None;
+            assert_eq!(transaction.verify_signature(), Ok(()));
         }
     }
 }
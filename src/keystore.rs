@@ -0,0 +1,275 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::KeyInit;
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::components::signature::Pair;
+use crate::components::signature::PublicKey;
+use crate::components::signature::SEED_LEN;
+
+pub type KeystoreResult<T> = Result<T, Cow<'static, str>>;
+
+pub const KEYSTORE_DIR: &str = "keystore";
+pub const DEFAULT_UNLOCK_TIMEOUT: Duration = Duration::from_secs(300);
+
+const SALT_LEN: usize = 16;
+const CIPHER_NONCE_LEN: usize = 24;
+const CIPHER_KEY_LEN: usize = 32;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct KdfParams {
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+/// Key as stored on disk. Seed is encrypted with XChaCha20Poly1305
+/// under a key derived from passphrase by Argon2id
+#[derive(Clone, Serialize, Deserialize)]
+struct KeyFile {
+    name: String,
+    public: PublicKey,
+    kdf: KdfParams,
+    cipher_nonce: String,
+    ciphertext: String,
+}
+
+struct UnlockedKey {
+    pair: Pair,
+    until: Option<Instant>,
+}
+
+struct StoredKey {
+    key_file: KeyFile,
+    unlocked: Option<UnlockedKey>,
+}
+
+/// Named keys kept under `<data_dir>/keystore/<name>.json`
+pub struct Keystore {
+    path: PathBuf,
+    keys: BTreeMap<String, StoredKey>,
+}
+
+impl Keystore {
+    pub fn open(data_dir: impl Into<PathBuf>) -> KeystoreResult<Self> {
+        let path = data_dir.into().join(KEYSTORE_DIR);
+        std::fs::create_dir_all(&path)
+            .map_err(|e| format!("Cannot create keystore directory {path:?}: {e}"))?;
+
+        let mut keystore = Keystore {
+            path,
+            keys: BTreeMap::new(),
+        };
+        keystore.load()?;
+
+        Ok(keystore)
+    }
+
+    fn load(&mut self) -> KeystoreResult<()> {
+        let entries = std::fs::read_dir(&self.path)
+            .map_err(|e| format!("Cannot read keystore directory: {e}"))?;
+
+        for entry in entries {
+            let file_path = entry
+                .map_err(|e| format!("Cannot read keystore entry: {e}"))?
+                .path();
+            if file_path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let content = std::fs::read_to_string(&file_path)
+                .map_err(|e| format!("Cannot read key file {file_path:?}: {e}"))?;
+            let key_file: KeyFile = serde_json::from_str(&content)
+                .map_err(|e| format!("Malformed key file {file_path:?}: {e}"))?;
+
+            let stored_key = StoredKey {
+                key_file,
+                unlocked: None,
+            };
+            self.keys.insert(stored_key.key_file.name.clone(), stored_key);
+        }
+
+        Ok(())
+    }
+
+    pub fn generate(&mut self, name: &str, passphrase: &str) -> KeystoreResult<&Pair> {
+        self.insert(name, Pair::generate(), passphrase)
+    }
+
+    /// Import from `0x` hex seed or secret uri (see `Pair::from_suri`)
+    pub fn import(&mut self, name: &str, secret: &str, passphrase: &str) -> KeystoreResult<&Pair> {
+        let pair = Pair::from_suri(secret)?;
+        self.insert(name, pair, passphrase)
+    }
+
+    /// Decrypt the key so it can sign. `None` timeout keeps it unlocked until `lock()`
+    pub fn unlock(&mut self, name: &str, passphrase: &str, timeout: Option<Duration>) -> KeystoreResult<()> {
+        let stored_key = self.keys
+            .get_mut(name)
+            .ok_or_else(|| format!("No key named `{name}` in keystore"))?;
+
+        let pair = decrypt_seed(&stored_key.key_file, passphrase)?;
+        stored_key.unlocked = Some(UnlockedKey {
+            pair,
+            until: timeout.map(|timeout| Instant::now() + timeout),
+        });
+
+        Ok(())
+    }
+
+    /// Unlock every key that opens with given passphrase. Returns how many did
+    pub fn unlock_all(&mut self, passphrase: &str, timeout: Option<Duration>) -> usize {
+        let names = self.keys.keys().cloned().collect::<Vec<_>>();
+        names
+            .into_iter()
+            .filter(|name| self.unlock(name, passphrase, timeout).is_ok())
+            .count()
+    }
+
+    pub fn lock(&mut self, name: &str) -> KeystoreResult<()> {
+        self.keys
+            .get_mut(name)
+            .ok_or_else(|| format!("No key named `{name}` in keystore"))?
+            .unlocked = None;
+
+        Ok(())
+    }
+
+    pub fn is_unlocked(&self, name: &str) -> bool {
+        self.get(name).is_ok()
+    }
+
+    pub fn get(&self, name: &str) -> KeystoreResult<&Pair> {
+        let stored_key = self.keys
+            .get(name)
+            .ok_or_else(|| format!("No key named `{name}` in keystore"))?;
+
+        match &stored_key.unlocked {
+            Some(unlocked) if unlocked.until.is_none_or(|until| Instant::now() < until) =>
+                Ok(&unlocked.pair),
+            _ => Err(format!("Key `{name}` is locked. Use unlock first").into()),
+        }
+    }
+
+    pub fn public(&self, name: &str) -> KeystoreResult<&PublicKey> {
+        self.keys
+            .get(name)
+            .map(|stored_key| &stored_key.key_file.public)
+            .ok_or_else(|| format!("No key named `{name}` in keystore").into())
+    }
+
+    pub fn list(&self) -> impl Iterator<Item = (&String, &PublicKey)> {
+        self.keys
+            .iter()
+            .map(|(name, stored_key)| (name, &stored_key.key_file.public))
+    }
+
+    fn insert(&mut self, name: &str, pair: Pair, passphrase: &str) -> KeystoreResult<&Pair> {
+        if name.is_empty() || !name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-') {
+            Err("Key name can only contain alphanumeric, `_` and `-`")?;
+        }
+        if self.keys.contains_key(name) {
+            Err(format!("Key named `{name}` already exists"))?;
+        }
+        if passphrase.is_empty() {
+            Err("Passphrase cannot be empty")?;
+        }
+
+        let key_file = encrypt_seed(name, &pair, passphrase)?;
+        let content = serde_json::to_string_pretty(&key_file)
+            .map_err(|e| format!("Cannot serialize key file: {e}"))?;
+        let file_path = self.path.join(format!("{name}.json"));
+        std::fs::write(&file_path, content)
+            .map_err(|e| format!("Cannot write key file {file_path:?}: {e}"))?;
+
+        let stored_key = StoredKey {
+            key_file,
+            unlocked: Some(UnlockedKey {
+                pair,
+                until: Some(Instant::now() + DEFAULT_UNLOCK_TIMEOUT),
+            }),
+        };
+        let stored_key = self.keys.entry(name.to_string()).or_insert(stored_key);
+
+        Ok(&stored_key.unlocked.as_ref().expect("Inserted as unlocked").pair)
+    }
+}
+
+fn derive_cipher_key(passphrase: &str, kdf: &KdfParams) -> KeystoreResult<[u8; CIPHER_KEY_LEN]> {
+    let salt: [u8; SALT_LEN] = decode_hex(&kdf.salt, "salt")?;
+    let params = argon2::Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(CIPHER_KEY_LEN))
+        .map_err(|e| format!("Invalid key derivation parameters: {e}"))?;
+    let argon2 = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    let mut cipher_key = [0u8; CIPHER_KEY_LEN];
+    argon2.hash_password_into(passphrase.as_bytes(), &salt, &mut cipher_key)
+        .map_err(|e| format!("Cannot derive key from passphrase: {e}"))?;
+
+    Ok(cipher_key)
+}
+
+fn encrypt_seed(name: &str, pair: &Pair, passphrase: &str) -> KeystoreResult<KeyFile> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut cipher_nonce = [0u8; CIPHER_NONCE_LEN];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    rand::rngs::OsRng.fill_bytes(&mut cipher_nonce);
+
+    let default_params = argon2::Params::default();
+    let kdf = KdfParams {
+        salt: format!("0x{}", hex::encode(salt)),
+        m_cost: default_params.m_cost(),
+        t_cost: default_params.t_cost(),
+        p_cost: default_params.p_cost(),
+    };
+
+    let cipher = XChaCha20Poly1305::new(&derive_cipher_key(passphrase, &kdf)?.into());
+    let ciphertext = cipher.encrypt(&cipher_nonce.into(), pair.seed().as_slice())
+        .map_err(|_| "Cannot encrypt seed")?;
+
+    Ok(KeyFile {
+        name: name.to_string(),
+        public: pair.public(),
+        kdf,
+        cipher_nonce: format!("0x{}", hex::encode(cipher_nonce)),
+        ciphertext: format!("0x{}", hex::encode(ciphertext)),
+    })
+}
+
+fn decrypt_seed(key_file: &KeyFile, passphrase: &str) -> KeystoreResult<Pair> {
+    let cipher_nonce: [u8; CIPHER_NONCE_LEN] = decode_hex(&key_file.cipher_nonce, "cipher nonce")?;
+    let ciphertext = hex::decode(key_file.ciphertext.trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid hex value for ciphertext: {e}"))?;
+
+    let cipher = XChaCha20Poly1305::new(&derive_cipher_key(passphrase, &key_file.kdf)?.into());
+    let seed = cipher.decrypt(&cipher_nonce.into(), ciphertext.as_slice())
+        .map_err(|_| "Wrong passphrase or corrupted key file")?;
+    let seed: [u8; SEED_LEN] = seed.try_into()
+        .map_err(|_| "Decrypted seed has unexpected length")?;
+
+    let pair = Pair::from_seed(&seed);
+    if pair.public() != key_file.public {
+        Err("Decrypted seed do not match stored public key")?;
+    }
+
+    Ok(pair)
+}
+
+fn decode_hex<const N: usize>(value: &str, what: &str) -> KeystoreResult<[u8; N]> {
+    let value = value.trim();
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    let bytes = hex::decode(value)
+        .map_err(|e| format!("Invalid hex value for {what}: {e}"))?;
+    let bytes_len = bytes.len();
+
+    bytes.try_into()
+        .map_err(|_| format!("Expected {N} bytes for {what}, got {bytes_len}").into())
+}
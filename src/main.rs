@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 use std::io::stdin;
 use std::io::stdout;
@@ -6,22 +8,63 @@ use std::io::Write;
 
 pub mod components;
 pub mod chain;
+pub mod keystore;
+pub mod mining;
 
 use chain::ChainProperties;
+use chain::GenesisConfig;
 use chain::Chain;
 use components::consensus::Consensus;
+use components::consensus::ConsensusEngine;
 use components::consensus::ProofOfWork;
+use components::difficulty::Target;
+use components::error::ConsensusError;
+use components::difficulty::MAX_DIFFICULTY_BITS;
 use components::Balance;
+use components::BlockNumber;
+use components::ChainId;
+use components::AccountId;
+use components::multisig::MultisigInfo;
+use components::multisig::MultisigThreshold;
+use components::AddressPrefix;
+use components::account::DEFAULT_ADDRESS_PREFIX;
+use components::account::MAX_ADDRESS_PREFIX;
+use components::hasher::HashAlgorithm;
+use components::transaction::Operation;
 use components::transaction::Transaction;
+use components::origin::Origin;
+use components::signature::Pair;
+use components::signature::PublicKey;
+use keystore::Keystore;
+use keystore::DEFAULT_UNLOCK_TIMEOUT;
+use mining::AutoMiner;
+use mining::SharedNode;
 use components::block::Block;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+pub const DEFAULT_DATA_DIR: &str = "suchain_data";
+pub const PASSPHRASE_ENV: &str = "SUCHAIN_KEYSTORE_PASSPHRASE";
+
 fn main() -> Result<(), i32> {
     println!(".\n.\n.\n");
 
-    let mut node: Option<Chain> = None;
+    let data_dir = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_DATA_DIR.to_string());
+    let mut keystore = Keystore::open(&data_dir).map_err(|e| {
+        println!("Cannot open keystore in {data_dir}. Error: {e}");
+        1
+    })?;
+    // Keys opened at startup stay unlocked for lifetime of node
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        let unlocked_count = keystore.unlock_all(&passphrase, None);
+        println!("Unlocked {unlocked_count} key(s) from {PASSPHRASE_ENV}");
+    }
+
+    let node: SharedNode = Arc::new(Mutex::new(None));
+    let mut auto_miner: Option<AutoMiner> = None;
     loop {
         print!("\n>>");
         stdout().flush().expect("Error while printing to stdout..");
@@ -32,30 +75,106 @@ fn main() -> Result<(), i32> {
         if input.is_empty() || input.starts_with("//") {
             continue;
         }
-        println!(">>{input}");
+        println!(">>{}", redact_secrets(&input));
 
         let command = Command::construct(input);
-        command.execute(&mut node);
+        command.execute(&node, &mut keystore, &mut auto_miner);
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NewNodeConfig {
+    // Leading zero bits required in block hash
+    difficulty: u32,
+    allowance: Balance,
+    minimum_balance: Balance,
+    #[serde(default = "default_chain_id")]
+    chain_id: ChainId,
+    #[serde(default = "default_address_prefix")]
+    address_prefix: AddressPrefix,
+    // Kept as string since it can only be parsed once address_prefix is known
+    #[serde(default)]
+    sudo: Option<String>,
+    #[serde(default)]
+    consensus: ConsensusEngine,
+    #[serde(default)]
+    authorities: Vec<PublicKey>,
+    #[serde(default = "default_slot_duration")]
+    slot_duration: u64,
+    // Address to stake bonded at genesis. Addresses kept as string like sudo
+    #[serde(default)]
+    stakers: BTreeMap<String, Balance>,
+    #[serde(default = "default_unbonding_period")]
+    unbonding_period: BlockNumber,
+    #[serde(default = "default_target_block_time")]
+    target_block_time: u64,
+    #[serde(default = "default_retarget_half_life")]
+    retarget_half_life: u64,
+    #[serde(default = "default_max_future_drift")]
+    max_future_drift: u64,
+    // Fixed so every node given same config derives same genesis
+    #[serde(default)]
+    genesis_timestamp: u64,
+    #[serde(default)]
+    hasher: HashAlgorithm,
+    // Defaults to available parallelism
+    #[serde(default)]
+    miner_threads: Option<usize>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub enum Command {
     Clear,
     Exit,
     ShowNode,
     Help,
-    NewNode {
-        difficulty: usize,
-        allowance: Balance,
-        minimum_balance: Balance,
-    },
+    NewNode(NewNodeConfig),
     Operation(Transaction),
     Error(String),
     Unknown(String),
-    IncreaseDifficulty(usize),
+    IncreaseDifficulty(Target),
     AddRawBlock(Block),
     GetLastBlock,
+    // Commands carrying secrets are only taken in word syntax,
+    // since raw JSON input would be echoed unmasked
+    #[serde(skip_deserializing)]
+    GenerateKey {
+        name: String,
+        passphrase: String,
+    },
+    ListKeys,
+    #[serde(skip_deserializing)]
+    ImportKey {
+        name: String,
+        secret: String,
+        passphrase: String,
+    },
+    GeneratePhrase,
+    #[serde(skip_deserializing)]
+    InspectKey(String),
+    MultisigAddress {
+        signatories: Vec<AccountId>,
+        threshold: MultisigThreshold,
+    },
+    #[serde(skip_deserializing)]
+    Unlock {
+        name: String,
+        passphrase: String,
+        timeout: Option<u64>,
+    },
+    Lock(String),
+    ExportPubkey(String),
+    SignAndSubmit {
+        key: String,
+        operation: Operation,
+    },
+    SetAuthor(String),
+    ShowForks,
+    VerifyChain,
+    SetMinerThreads(usize),
+    StartMining(Option<Duration>),
+    StopMining,
+    MiningStatus,
 }
 
 impl Command {
@@ -98,12 +217,9 @@ impl Command {
                 }
             }
             "increase_difficulty" | "set_difficulty" => {
-                let difficulty_cmd_res = rest.trim().parse::<usize>()
-                    .map_err(|_| "Invalid difficulty paramater. Should have been a number")
-                    .map(|new_difficulty| Command::IncreaseDifficulty(new_difficulty));
-                match difficulty_cmd_res {
-                    Ok(v) => v,
-                    Err(e) => Command::Error(e.into()),
+                match rest.parse::<Target>() {
+                    Ok(target) => Command::IncreaseDifficulty(target),
+                    Err(e) => Command::Error(e),
                 }
             }
             "add_raw_block" => {
@@ -118,93 +234,570 @@ impl Command {
 
             }
             "get_last_block" | "last_block" => Command::GetLastBlock,
+            "show_forks" | "forks" => Command::ShowForks,
+            "verify_chain" => Command::VerifyChain,
+            "set_miner_threads" => {
+                match rest.trim().parse::<usize>() {
+                    Ok(threads) if threads > 0 => Command::SetMinerThreads(threads),
+                    _ => Command::Error("Invalid thread count. Should have been a positive number".into()),
+                }
+            }
+            "start_mining" => {
+                match rest.trim() {
+                    "" => Command::StartMining(None),
+                    interval => match interval.parse::<u64>() {
+                        Ok(interval) if interval > 0 => Command::StartMining(Some(Duration::from_secs(interval))),
+                        _ => Command::Error("Invalid block interval. Should have been positive seconds as number".into()),
+                    },
+                }
+            }
+            "stop_mining" => Command::StopMining,
+            "mining_status" => Command::MiningStatus,
+            "generate_key" => {
+                match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [name, passphrase] => Command::GenerateKey {
+                        name: name.to_string(),
+                        passphrase: passphrase.to_string(),
+                    },
+                    _ => Command::Error("Usage: generate_key <name> <passphrase>".into()),
+                }
+            }
+            "list_keys" => Command::ListKeys,
+            "export_pubkey" => Command::ExportPubkey(rest.trim().to_string()),
+            "import_key" => {
+                // secret may be a mnemonic phrase with spaces in it
+                match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [name, secret @ .., passphrase] if !secret.is_empty() => Command::ImportKey {
+                        name: name.to_string(),
+                        secret: secret.join(" "),
+                        passphrase: passphrase.to_string(),
+                    },
+                    _ => Command::Error("Usage: import_key <name> <seed-hex|secret-uri> <passphrase>".into()),
+                }
+            }
+            "generate_phrase" => Command::GeneratePhrase,
+            "inspect_key" => Command::InspectKey(rest.trim().to_string()),
+            "multisig_address" => {
+                let mut args = rest.split_whitespace();
+                let threshold = args.next()
+                    .unwrap_or_default()
+                    .parse::<MultisigThreshold>()
+                    .map_err(|_| "Invalid threshold paramater. Should have been a number".to_string());
+                let signatories = args
+                    .map(|address| address.parse::<AccountId>())
+                    .collect::<Result<Vec<_>, _>>();
+                match (threshold, signatories) {
+                    (Ok(threshold), Ok(signatories)) => Command::MultisigAddress { signatories, threshold },
+                    (Err(e), _) | (_, Err(e)) => Command::Error(e),
+                }
+            }
+            "unlock" => {
+                let unlock_cmd_res = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [name, passphrase] => Ok((name.to_string(), passphrase.to_string(), None)),
+                    [name, passphrase, timeout] => timeout
+                        .parse::<u64>()
+                        .map(|timeout| (name.to_string(), passphrase.to_string(), Some(timeout)))
+                        .map_err(|_| "Invalid timeout paramater. Should have been seconds as number"),
+                    _ => Err("Usage: unlock <name> <passphrase> [timeout-seconds]"),
+                };
+                match unlock_cmd_res {
+                    Ok((name, passphrase, timeout)) => Command::Unlock { name, passphrase, timeout },
+                    Err(e) => Command::Error(e.into()),
+                }
+            }
+            "lock" => Command::Lock(rest.trim().to_string()),
+            "set_author" => Command::SetAuthor(rest.trim().to_string()),
+            "sign_and_submit" => {
+                let (key, operation) = rest
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((rest.trim(), ""));
+                match serde_json::from_str(operation) {
+                    Ok(operation) => Command::SignAndSubmit {
+                        key: key.to_string(),
+                        operation,
+                    },
+                    Err(err) => {
+                        Command::Error(
+                            format!("While parsing SignAndSubmit. Error: {:?}", err)
+                        )
+                    },
+                }
+            }
             cmd => {
                 let convert_res = serde_json::from_str(input.as_str());
                 match convert_res {
                     Ok(v) => v,
-                    Err(_) => Command::Unknown(cmd.to_string()),
+                    Err(_) => Command::Unknown(redact_secrets(cmd)),
                 }
             },
         }
     }
 
-    fn execute(self, node: &mut Option<Chain>) {
+    fn execute(self, shared_node: &SharedNode, keystore: &mut Keystore, auto_miner: &mut Option<AutoMiner>) {
+        // Background miner takes node lock itself, so these must run without holding it
+        match self {
+            Command::StartMining(interval) => return start_mining(shared_node, auto_miner, interval),
+            Command::StopMining => return stop_mining(shared_node, auto_miner),
+            Command::MiningStatus => return mining_status(shared_node, auto_miner.as_ref()),
+            _ => {}
+        }
+
+        let mut node = mining::lock_node(shared_node);
+        let node = &mut *node;
+        let auto_mining = auto_miner.is_some();
         match self {
             Command::Clear => clear_screen(),
             Command::Exit => exit_program(),
             Command::ShowNode => show_node(node),
             Command::Help => show_help(),
-            Command::NewNode { difficulty, allowance, minimum_balance} =>
-                new_node(node, difficulty, allowance, minimum_balance),
+            Command::NewNode(config) => new_node(node, config),
             Command::Unknown(command) => unknown_command(&command),
             Command::Error(err) => println!("Error parsing comand: {err}"),
-            Command::Operation(op) => perform_operation(node, op),
-            Command::IncreaseDifficulty(difficulty) =>
-                increase_difficulty(node, difficulty),
+            Command::Operation(op) => perform_operation(node, op, auto_mining),
+            Command::IncreaseDifficulty(target) =>
+                increase_difficulty(node, target),
             Command::AddRawBlock(block) =>
                 add_raw_block(node, block),
             Command::GetLastBlock =>
-                get_last_block(node)
+                get_last_block(node),
+            Command::ShowForks =>
+                show_forks(node),
+            Command::VerifyChain =>
+                verify_chain(node),
+            Command::SetMinerThreads(threads) =>
+                set_miner_threads(node, threads),
+            Command::GenerateKey { name, passphrase } =>
+                generate_key(keystore, &name, &passphrase),
+            Command::ListKeys =>
+                list_keys(keystore),
+            Command::ImportKey { name, secret, passphrase } =>
+                import_key(keystore, &name, &secret, &passphrase),
+            Command::GeneratePhrase =>
+                println!("{}", Pair::generate_phrase()),
+            Command::InspectKey(suri) =>
+                inspect_key(&suri),
+            Command::MultisigAddress { signatories, threshold } =>
+                multisig_address(signatories, threshold),
+            Command::Unlock { name, passphrase, timeout } =>
+                unlock_key(keystore, &name, &passphrase, timeout),
+            Command::Lock(name) =>
+                lock_key(keystore, &name),
+            Command::ExportPubkey(name) =>
+                export_pubkey(keystore, &name),
+            Command::SignAndSubmit { key, operation } =>
+                sign_and_submit(node, keystore, &key, operation, auto_mining),
+            Command::SetAuthor(name) =>
+                set_author(node, keystore, &name),
+            Command::StartMining(_) | Command::StopMining | Command::MiningStatus =>
+                unreachable!("Mining commands are handled before locking node"),
         }
     }
 }
 
-fn perform_operation(node_container: &mut Option<Chain>, transaction: Transaction) {
+fn perform_operation(node_container: &mut Option<Chain>, transaction: Transaction, auto_mining: bool) {
     match node_container {
         None => println!("No node loaded. Use new_node operation first"),
+        Some(node) if auto_mining => {
+            // Rejected here so a bad signature can not hold back the pool
+            if let Err(e) = transaction.verify_signature() {
+                println!("Can not queue this transaction. Error: {e}");
+                return;
+            }
+            node.pending_transactions.push(transaction);
+            println!("Queued for background miner. {} transaction(s) pending", node.pending_transactions.len());
+        }
         Some(node) => {
             let mut block = Block::new(node);
+            // Left over by a stopped background miner. Their nonces come first
+            let take_count = node.pending_transactions.len().min(mining::MAX_BLOCK_TRANSACTIONS - 1);
+            block.transactions = node.pending_transactions.drain(..take_count).collect();
+            let pending_count = block.transactions.len();
             block.transactions.push(transaction);
- 
-            let prep_res = <ProofOfWork as Consensus>::prepare_block(node, &mut block);
-            if let Err(prep_err) = prep_res {
-                println!("While preparing block with this transaction. {prep_err:?}");
+
+            let prep_res = <ConsensusEngine as Consensus>::prepare_block(node, &mut block);
+            match prep_res {
+                Err(prep_err) => println!("While preparing block with this transaction. {prep_err}"),
+                Ok(()) if node.properties.consensus == ConsensusEngine::ProofOfWork => {
+                    if let Some(report) = node.miner.last_report() {
+                        println!("Sealed after {report}");
+                    }
+                }
+                Ok(()) => {}
             }
 
-            let add_res = node.add_block(block);
+            let add_res = node.add_block(block.clone());
             if let Err(tx_err) = add_res {
-                println!("Can not perform this transaction. While adding block Error: {tx_err:?}");
+                println!("Can not perform this transaction. While adding block Error: {tx_err}");
+
+                // Pending ones go back to the pool unless one of them is what failed
+                let mut pending = block.transactions;
+                pending.truncate(pending_count);
+                if let ConsensusError::Transaction { tx_index, .. } = tx_err {
+                    if tx_index < pending_count {
+                        pending.remove(tx_index);
+                    }
+                }
+                if !pending.is_empty() {
+                    println!("{} transaction(s) left pending", pending.len());
+                }
+                node.pending_transactions.splice(0..0, pending);
             }
         }
     }
 }
 
+fn sign_and_submit(
+    node_container: &mut Option<Chain>,
+    keystore: &Keystore,
+    key: &str,
+    operation: Operation,
+    auto_mining: bool,
+) {
+    let Some(node) = node_container else {
+        println!("No node loaded. Use new_node operation first");
+        return;
+    };
+    let pair = match keystore.get(key) {
+        Ok(pair) => pair,
+        Err(e) => {
+            println!("While signing transaction. Error: {e}");
+            return;
+        }
+    };
+
+    let account_id = pair.public().to_account_id();
+    let transaction = Transaction {
+        operation,
+        nonce: node.next_account_nonce(&account_id),
+        chain_id: node.properties.chain_id,
+        initiator: Origin::Signed(account_id),
+        signature: None,
+    }.sign(pair);
+
+    perform_operation(node_container, transaction, auto_mining);
+}
+
+fn generate_key(keystore: &mut Keystore, name: &str, passphrase: &str) {
+    match keystore.generate(name, passphrase) {
+        Ok(pair) => print_key(name, &pair.public(), true),
+        Err(e) => println!("While generating key. Error: {e}"),
+    }
+}
+
+fn import_key(keystore: &mut Keystore, name: &str, secret: &str, passphrase: &str) {
+    match keystore.import(name, secret, passphrase) {
+        Ok(pair) => print_key(name, &pair.public(), true),
+        Err(e) => println!("While importing key. Error: {e}"),
+    }
+}
+
+fn inspect_key(suri: &str) {
+    match Pair::from_suri(suri) {
+        Ok(pair) => {
+            let public = pair.public();
+            let account_id = public.to_account_id();
+            println!("public key {public}, account {account_id}");
+        }
+        Err(e) => println!("While inspecting key. Error: {e}"),
+    }
+}
+
+fn multisig_address(signatories: Vec<AccountId>, threshold: MultisigThreshold) {
+    let multisig_info = MultisigInfo::new(signatories, threshold);
+    if !multisig_info.is_valid() {
+        println!("Threshold {threshold} is invalid for {} distinct signatories", multisig_info.signatories.len());
+        return;
+    }
+    println!("multisig account {}", multisig_info.account_id());
+}
+
+fn unlock_key(keystore: &mut Keystore, name: &str, passphrase: &str, timeout: Option<u64>) {
+    let timeout = timeout
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_UNLOCK_TIMEOUT);
+    match keystore.unlock(name, passphrase, Some(timeout)) {
+        Ok(()) => println!("Key `{name}` unlocked for {} seconds", timeout.as_secs()),
+        Err(e) => println!("While unlocking key. Error: {e}"),
+    }
+}
+
+fn lock_key(keystore: &mut Keystore, name: &str) {
+    if let Err(e) = keystore.lock(name) {
+        println!("While locking key. Error: {e}");
+    }
+}
+
+fn export_pubkey(keystore: &Keystore, name: &str) {
+    match keystore.public(name) {
+        Ok(public) => print_key(name, public, keystore.is_unlocked(name)),
+        Err(e) => println!("While exporting key. Error: {e}"),
+    }
+}
+
+fn set_author(node_container: &mut Option<Chain>, keystore: &Keystore, name: &str) {
+    let Some(node) = node_container else {
+        println!("No node loaded. Use new_node operation first");
+        return;
+    };
+
+    match keystore.get(name) {
+        Ok(pair) => {
+            let can_seal = match node.properties.consensus {
+                ConsensusEngine::ProofOfWork => true,
+                ConsensusEngine::ProofOfAuthority => node.properties.authorities.contains(&pair.public()),
+                ConsensusEngine::ProofOfStake => node.staking.ledgers
+                    .get(&pair.public().to_account_id())
+                    .is_some_and(|ledger| ledger.bonded > 0),
+            };
+            if !can_seal {
+                println!("Warning: key `{name}` can not seal blocks of this chain yet");
+            }
+            node.author_key = Some(pair.clone());
+            println!("Blocks will be sealed with key `{name}`");
+        }
+        Err(e) => println!("While setting author. Error: {e}"),
+    }
+}
+
+fn list_keys(keystore: &Keystore) {
+    for (name, public) in keystore.list() {
+        print_key(name, public, keystore.is_unlocked(name));
+    }
+}
+
+fn print_key(name: &str, public: &PublicKey, unlocked: bool) {
+    let account_id = public.to_account_id();
+    let lock_status = if unlocked { "unlocked" } else { "locked" };
+    println!("{name} ({lock_status}): public key {public}, account {account_id}");
+}
+
 fn add_raw_block(node_container: &mut Option<Chain>, block: Block) {
     match node_container {
         None => println!("No node loaded. Use new_node operation first"),
         Some(node) => {
             let add_res = node.add_block(block);
             if let Err(tx_err) = add_res {
-                println!("While adding block. Error: {tx_err:?}");
+                println!("While adding block. Error: {tx_err}");
             }
         }
     }
 }
 
-fn increase_difficulty(node_container: &mut Option<Chain>, new_difficulty: usize) {
+fn set_miner_threads(node_container: &mut Option<Chain>, threads: usize) {
     match node_container {
         None => println!("No node loaded. Use new_node operation first"),
         Some(node) => {
-            node.properties.difficulty = new_difficulty;
+            node.miner.set_threads(threads);
+            println!("Mining on {} thread(s)", node.miner.threads());
         }
     }
 }
 
-fn new_node(
-    node_container: &mut Option<Chain>,
-    difficulty: usize,
-    allowance: Balance,
-    exestinsial_deposit: Balance
-){
+fn verify_chain(node_container: &mut Option<Chain>) {
+    match node_container {
+        None => println!("No node loaded. Use new_node operation first"),
+        Some(node) => match node.validate_from_genesis() {
+            Ok(()) => println!("Chain is valid. Replayed {} block(s) after genesis", node.blocks.len() - 1),
+            Err(e) => println!("Chain is invalid. {e}"),
+        },
+    }
+}
+
+fn start_mining(shared_node: &SharedNode, auto_miner: &mut Option<AutoMiner>, interval: Option<Duration>) {
+    if let Some(running) = auto_miner {
+        println!("Already mining a block every {}s. Use stop_mining first", running.interval().as_secs());
+        return;
+    }
+    let interval = match (interval, mining::lock_node(shared_node).as_ref()) {
+        (_, None) => {
+            println!("No node loaded. Use new_node operation first");
+            return;
+        }
+        (Some(interval), Some(_)) => interval,
+        (None, Some(node)) => mining::default_block_interval(&node.properties),
+    };
+
+    *auto_miner = Some(AutoMiner::start(shared_node.clone(), interval));
+    println!("Mining a block every {}s in background. Operations are queued until included", interval.as_secs());
+}
+
+fn stop_mining(shared_node: &SharedNode, auto_miner: &mut Option<AutoMiner>) {
+    let Some(running) = auto_miner.take() else {
+        println!("Background miner is not running");
+        return;
+    };
+
+    let status = running.stop(shared_node);
+    println!("Stopped background miner after {} block(s)", status.blocks_produced);
+    let pending_count = mining::lock_node(shared_node)
+        .as_ref()
+        .map_or(0, |node| node.pending_transactions.len());
+    if pending_count > 0 {
+        println!("{pending_count} transaction(s) stay pending. They are sealed ahead of next submitted operation");
+    }
+}
+
+fn mining_status(shared_node: &SharedNode, auto_miner: Option<&AutoMiner>) {
+    let Some(running) = auto_miner else {
+        println!("Background miner is not running");
+        return;
+    };
+
+    let status = running.status();
+    println!("Mining a block every {}s", running.interval().as_secs());
+    println!("Blocks produced: {}", status.blocks_produced);
+    println!("Transactions included: {}", status.transactions_included);
+    println!("Transactions dropped: {}", status.transactions_dropped);
+    if let Some((height, hash)) = status.last_block {
+        println!("Last block: #{height} {hash}");
+    }
+    if let Some(last_error) = status.last_error {
+        println!("Last error: {last_error}");
+    }
+    if let Some(node) = mining::lock_node(shared_node).as_ref() {
+        println!("Pending transactions: {}", node.pending_transactions.len());
+        if let Some(report) = node.miner.last_report() {
+            println!("Last seal search: {report}");
+        }
+    }
+}
+
+fn show_forks(node_container: &mut Option<Chain>) {
+    let Some(node) = node_container else {
+        println!("No node loaded. Use new_node operation first");
+        return;
+    };
+
+    let canonical = node.canonical_hashes();
+    let mut leaves = node.block_tree.leaves();
+    leaves.sort_by_key(|(_, leaf)| std::cmp::Reverse(leaf.cumulative_work));
+
+    for (leaf_hash, leaf) in leaves {
+        let height = leaf.block.header.height;
+        let work = leaf.cumulative_work;
+        if canonical.contains_key(leaf_hash) {
+            println!("{leaf_hash}: height {height}, cumulative work {work} (canonical tip)");
+            continue;
+        }
+
+        let branch = node.block_tree.branch(leaf_hash, |hash| canonical.contains_key(hash));
+        let fork_height = branch
+            .first()
+            .map(|block| block.header.height.saturating_sub(1))
+            .unwrap_or_default();
+        println!(
+            "{leaf_hash}: height {height}, cumulative work {work}, {} block(s) forked after canonical height {fork_height}",
+            branch.len(),
+        );
+    }
+}
+
+fn increase_difficulty(node_container: &mut Option<Chain>, new_target: Target) {
+    match node_container {
+        None => println!("No node loaded. Use new_node operation first"),
+        Some(node) if !node.properties.target_block_time.is_zero() =>
+            println!("Target is retargeted every block. Create node with \"target_block_time\": 0 to set it manually"),
+        Some(node) => {
+            node.set_fixed_target(new_target);
+        }
+    }
+}
+
+fn new_node(node_container: &mut Option<Chain>, config: NewNodeConfig) {
+    let NewNodeConfig {
+        difficulty,
+        allowance,
+        minimum_balance: exestinsial_deposit,
+        chain_id,
+        address_prefix,
+        sudo,
+        consensus,
+        authorities,
+        slot_duration,
+        stakers,
+        unbonding_period,
+        target_block_time,
+        retarget_half_life,
+        max_future_drift,
+        genesis_timestamp,
+        hasher,
+        miner_threads,
+    } = config;
+
+    if address_prefix > MAX_ADDRESS_PREFIX {
+        println!("Address prefix can not exceed {MAX_ADDRESS_PREFIX}");
+        return;
+    }
+    if difficulty > MAX_DIFFICULTY_BITS {
+        println!("Difficulty can not exceed {MAX_DIFFICULTY_BITS} leading zero bits");
+        return;
+    }
+
+    let sudo = match sudo.as_deref().map(|sudo| parse_address(sudo, address_prefix, hasher)).transpose() {
+        Ok(sudo) => sudo,
+        Err(e) => {
+            println!("Invalid sudo key. Error: {e}");
+            return;
+        }
+    };
+
+    let stakers = stakers
+        .iter()
+        .map(|(staker, stake)| parse_address(staker, address_prefix, hasher).map(|staker| (staker, *stake)))
+        .collect::<Result<Vec<_>, _>>();
+    let stakers = match stakers {
+        Ok(stakers) => stakers,
+        Err(e) => {
+            println!("Invalid staker. Error: {e}");
+            return;
+        }
+    };
+
+    if consensus == ConsensusEngine::ProofOfAuthority && authorities.is_empty() {
+        println!("ProofOfAuthority needs at least one authority");
+        return;
+    }
+    if consensus == ConsensusEngine::ProofOfStake && stakers.is_empty() {
+        println!("ProofOfStake needs at least one staker");
+        return;
+    }
+
     let prop = ChainProperties {
+        chain_id,
+        address_prefix,
         exestinsial_deposit,
-        difficulty,
+        target: Target::from_leading_zero_bits(difficulty),
         time_tolorant: Duration::from_secs(10),
-        consensus: ProofOfWork
+        consensus,
+        sudo,
+        authorities,
+        slot_duration: Duration::from_secs(slot_duration),
+        unbonding_period,
+        target_block_time: Duration::from_secs(target_block_time),
+        retarget_half_life: Duration::from_secs(retarget_half_life),
+        max_future_drift: Duration::from_secs(max_future_drift),
+        hasher,
+    };
+    let genesis_config = GenesisConfig {
+        properties: prop,
+        system_allowance: allowance,
+        stakers,
+        timestamp: genesis_timestamp,
     };
     let info = "suchain v0.1 testnet";
-    let chain = Chain::new(info.into(), prop, allowance);
+    let mut chain = match Chain::new(info.into(), genesis_config) {
+        Ok(chain) => chain,
+        Err(e) => {
+            println!("While building genesis state. Error: {e}");
+            return;
+        }
+    };
+    if let Some(miner_threads) = miner_threads {
+        chain.miner.set_threads(miner_threads);
+    }
 
+    println!("Genesis hash: {}", chain.genesis_hash());
     *node_container = Some(chain);
 }
 
@@ -213,12 +806,76 @@ pub fn get_last_block(node_container: &mut Option<Chain>) {
         None => println!("None"),
         Some(node) => {
             let last_block = node.get_latest_block();
-            let last_block_hash = last_block.get_hash();
+            let last_block_hash = last_block.get_hash(node.properties.hasher);
             println!("{last_block_hash}: {last_block}");
         }
     }
 }
 
+fn default_chain_id() -> ChainId {
+    1
+}
+
+fn default_address_prefix() -> AddressPrefix {
+    DEFAULT_ADDRESS_PREFIX
+}
+
+fn default_slot_duration() -> u64 {
+    5
+}
+
+fn default_unbonding_period() -> BlockNumber {
+    10
+}
+
+fn default_target_block_time() -> u64 {
+    10
+}
+
+fn default_retarget_half_life() -> u64 {
+    600
+}
+
+fn default_max_future_drift() -> u64 {
+    15
+}
+
+// Echoed command line with passphrases, seeds and phrases masked so they
+// do not end up in captured output. Only key name is kept
+fn redact_secrets(input: &str) -> String {
+    // Secret commands are refused as JSON, yet what was typed is still masked
+    let json_variant = input
+        .trim_start()
+        .strip_prefix("{\"")
+        .and_then(|rest| rest.split('"').next())
+        .unwrap_or_default();
+    let is_secret_variant = ["GenerateKey", "ImportKey", "Unlock", "InspectKey"]
+        .iter()
+        .any(|variant| variant.eq_ignore_ascii_case(json_variant));
+    if is_secret_variant {
+        return format!("{{\"{json_variant}\": ***}}");
+    }
+
+    let mut words = input.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let first_arg = words.next();
+    match (command.to_ascii_lowercase().as_str(), first_arg) {
+        ("generate_key" | "import_key" | "unlock", Some(name)) => format!("{command} {name} ***"),
+        ("inspect_key", Some(_)) => format!("{command} ***"),
+        _ => input.to_string(),
+    }
+}
+
+// Address given before chain exists can not go through AccountId::from_str
+// as global prefix is not set yet
+// Addresses given for a new chain are checksummed under that chain's hasher
+fn parse_address(address: &str, address_prefix: AddressPrefix, hasher: HashAlgorithm) -> Result<AccountId, String> {
+    match AccountId::from_address_under(address, hasher)? {
+        (prefix, account_id) if prefix == address_prefix => Ok(account_id),
+        (prefix, _) => Err(format!("Address {address} is for network prefix {prefix} but chain uses {address_prefix}")),
+    }
+}
+
 fn show_help() {
 println!(r##"
     suchain help.
@@ -234,15 +891,121 @@ println!(r##"
     - quit
            quit this program
 
-    - new_node {{"info": STRING, "difficulty": Number, "allowance": Balance}}
-           create a new node with given parameters
+    - generate_key <name> <passphrase>
+           create a new random key in keystore, encrypted with passphrase
+
+    - import_key <name> <seed-hex|secret-uri> <passphrase>
+           add a key to keystore, encrypted with passphrase. Secret uri is
+           `<mnemonic>//<junction>..` e.g `//Alice` (dev phrase) or `<12 words>//wallet//0`
+
+    - generate_phrase
+           print a new random mnemonic phrase
+
+    - inspect_key <seed-hex|secret-uri>
+           show public key and account of secret without storing it
+
+    - multisig_address <threshold> <address> <address>..
+           show account of multisig with given signatories and threshold
+           Use CreateMultisig, ProposeMultisig, ApproveMultisig and CancelMultisig
+           operations to manage it
+
+    - unlock <name> <passphrase> [timeout-seconds]
+           decrypt key so it can sign. Locks again after timeout (default 300)
+
+    - lock <name>
+           forget decrypted key until next unlock
+           (keys can also be unlocked at startup with SUCHAIN_KEYSTORE_PASSPHRASE env)
+
+    - list_keys
+           show name, public key and account of every key in keystore
+
+    - export_pubkey <name>
+           show public key and account of given key
+
+    - sign_and_submit <name> <operation-json>
+           sign given operation with named key and submit it in a new block
+
+    - new_node {{"difficulty": Number, "allowance": Balance, "minimum_balance": Balance, "chain_id": Number, "address_prefix": Number}}
+           create a new node with given parameters. chain_id (default 1) and
+           address_prefix (default 42) are optional. Accounts are written as checksummed
+           addresses encoded with address_prefix. Optional "sudo": Address sets the key
+           allowed to dispatch Root calls with Sudo operation. Optional "consensus"
+           selects the engine sealing blocks: "ProofOfWork" (default), "ProofOfAuthority"
+           or "ProofOfStake". "difficulty" is leading zero bits (at most 255) required in ProofOfWork hash.
+           ProofOfAuthority takes "authorities": [PublicKey] and "slot_duration": seconds
+           (default 5). Slots are assigned to authorities round-robin.
+           ProofOfWork retargets every block toward "target_block_time": seconds (default 10,
+           0 keeps target fixed). Target doubles or halves for each "retarget_half_life": seconds
+           (default 600) chain is behind or ahead of schedule.
+           ProofOfStake takes "stakers": {{Address: Balance}} bonded from allowance at genesis
+           and "unbonding_period": blocks (default 10). Slot leader is drawn weighted by stake.
+           Block timestamp may not be before its parent, must be after median of last 11
+           blocks and may be ahead of local clock by at most "max_future_drift": seconds (default 15).
+           Genesis block is derived from these parameters and "genesis_timestamp" (default 0),
+           so every node given same parameters starts from same genesis hash.
+           "hasher" picks hash function for blocks, account ids and addresses: "Sha3_256"
+           (default), "Blake2b256", "Keccak256" or "DoubleSha256". Sudo and staker addresses
+           must be encoded under that hasher. Keys derived from a phrase stay same on every chain
+
+    - set_difficulty <bits|0x-target>
+           set ProofOfWork target as leading zero bits or as 256-bit hex target.
+           Only for node created with "target_block_time": 0. Otherwise target follows
+           retargeting anchored at target recorded in genesis block. Applies from next
+           block on and is recorded, so verify_chain holds each block to target of its height
+
+    - set_miner_threads <count>
+           number of threads searching ProofOfWork seal. "miner_threads" in new_node
+           sets it at start (default available parallelism)
+
+    - show_forks
+           list tip of every known branch with its cumulative work. Branch with
+           most work is canonical and switching to it reverts and replays state
+
+    - start_mining [interval-seconds]
+           produce a block every interval in background, empty if nothing is pending.
+           Default interval is target_block_time (ProofOfWork) or slot_duration.
+           While mining, do_operation and sign_and_submit queue transactions instead
+           of sealing a block for each
+
+    - stop_mining
+           stop background miner. Queued transactions stay pending and are sealed
+           ahead of next submitted operation
+
+    - mining_status
+           show blocks produced by background miner, pending transactions and last error
+
+    - verify_chain
+           replay every block from genesis and report first invalid one. Age of
+           blocks is not checked, so chain can be verified any time after it was made
+
+    - set_author <name>
+           seal blocks of ProofOfAuthority or ProofOfStake chain with named key. Key must be unlocked
+
+    Staking operations (signed): {{"Bond": {{"amount": Balance}}}}, {{"Unbond": {{"amount": Balance}}}}
+           and "WithdrawUnbonded" once unbonding_period blocks have passed since Unbond
 "##);
 }
 
 fn show_node(node: &mut Option<Chain>) {
     match node {
         None => println!("None"),
-        Some(val) => println!("{val}")
+        Some(val) => {
+            println!("chain id: {}", val.properties.chain_id);
+            println!("genesis hash: {}", val.genesis_hash());
+            println!("address prefix: {}", val.properties.address_prefix);
+            println!("consensus: {:?}", val.properties.consensus);
+            println!("hasher: {:?}", val.properties.hasher);
+            if val.properties.consensus == ConsensusEngine::ProofOfWork {
+                let next_target = ProofOfWork::expected_target(val, val.get_latest_block());
+                println!("next block target: {next_target} ({} leading zero bits)", next_target.leading_zero_bits());
+            }
+            println!("cumulative work: {}", val.cumulative_work());
+            match &val.properties.sudo {
+                Some(sudo_key) => println!("sudo key: {sudo_key}"),
+                None => println!("sudo key: none"),
+            }
+            println!("{val}")
+        }
     }
     
 }
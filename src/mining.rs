@@ -0,0 +1,203 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::chain::Chain;
+use crate::chain::ChainProperties;
+use crate::components::block::Block;
+use crate::components::consensus::Consensus;
+use crate::components::consensus::ConsensusEngine;
+use crate::components::consensus::ProofOfWork;
+use crate::components::error::ConsensusError;
+use crate::components::hash::Hash;
+use crate::components::BlockNumber;
+
+/// Node shared between shell and background miner
+pub type SharedNode = Arc<Mutex<Option<Chain>>>;
+
+pub const DEFAULT_BLOCK_INTERVAL: Duration = Duration::from_secs(10);
+pub const MAX_BLOCK_TRANSACTIONS: usize = 256;
+// How often a waiting miner checks whether it was stopped
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub fn lock_node(node: &SharedNode) -> MutexGuard<'_, Option<Chain>> {
+    node.lock().expect("Node lock poisoned")
+}
+
+#[derive(Clone, Default)]
+pub struct MiningStatus {
+    pub blocks_produced: u64,
+    pub transactions_included: u64,
+    pub transactions_dropped: u64,
+    pub last_block: Option<(BlockNumber, Hash)>,
+    pub last_error: Option<String>,
+}
+
+/// Produces a block every `interval` on a background thread, empty if no
+/// transaction is pending. PoW seal is searched without holding the node
+/// so shell stays usable while mining
+pub struct AutoMiner {
+    interval: Duration,
+    stopped: Arc<AtomicBool>,
+    status: Arc<Mutex<MiningStatus>>,
+    worker: JoinHandle<()>,
+}
+
+impl AutoMiner {
+    pub fn start(node: SharedNode, interval: Duration) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let status = Arc::new(Mutex::new(MiningStatus::default()));
+
+        let worker = {
+            let stopped = stopped.clone();
+            let status = status.clone();
+            std::thread::spawn(move || run(&node, interval, &stopped, &status))
+        };
+
+        AutoMiner {
+            interval,
+            stopped,
+            status,
+            worker,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn status(&self) -> MiningStatus {
+        self.status.lock().expect("Mining status lock poisoned").clone()
+    }
+
+    /// Stop after the block in progress, cancelling its seal search. Transactions
+    /// still pending are kept and sealed ahead of next submitted operation.
+    /// Node must not be locked by caller
+    pub fn stop(self, node: &SharedNode) -> MiningStatus {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(chain) = lock_node(node).as_ref() {
+            chain.miner.cancel();
+        }
+        let status = self.status.clone();
+        self.worker.join().expect("Background miner panicked");
+
+        let final_status = status.lock().expect("Mining status lock poisoned").clone();
+        final_status
+    }
+}
+
+fn run(node: &SharedNode, interval: Duration, stopped: &AtomicBool, status: &Mutex<MiningStatus>) {
+    while !stopped.load(Ordering::Relaxed) {
+        let round_started = Instant::now();
+        let round_res = produce_block(node, stopped);
+
+        let mut status = status.lock().expect("Mining status lock poisoned");
+        let mut dropped_transaction = false;
+        match round_res {
+            Ok(Some((block, block_hash))) => {
+                status.blocks_produced += 1;
+                status.transactions_included += block.transactions.len() as u64;
+                status.last_block = Some((block.header.height, block_hash));
+                status.last_error = None;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                if let ConsensusError::Transaction { .. } = e {
+                    status.transactions_dropped += 1;
+                    dropped_transaction = true;
+                }
+                status.last_error = Some(e.to_string());
+            }
+        }
+        drop(status);
+
+        // Rest of the pool is retried right away instead of losing the interval
+        if dropped_transaction {
+            continue;
+        }
+        while !stopped.load(Ordering::Relaxed) && round_started.elapsed() < interval {
+            std::thread::sleep(STOP_POLL_INTERVAL.min(interval.saturating_sub(round_started.elapsed())));
+        }
+    }
+}
+
+// Build block from pending transactions and add it to node. Returns the block
+// with its hash, or None while no node is loaded or once stopped
+fn produce_block(node: &SharedNode, stopped: &AtomicBool) -> Result<Option<(Block, Hash)>, ConsensusError> {
+    let (mut block, target, hasher, miner, ticket) = {
+        let mut node = lock_node(node);
+        let Some(chain) = node.as_mut() else {
+            return Ok(None);
+        };
+        // Stop flag is raised before cancelling under the lock, so a job
+        // ticketed after that cancel is never started
+        if stopped.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let mut block = Block::new(chain);
+        let take_count = chain.pending_transactions.len().min(MAX_BLOCK_TRANSACTIONS);
+        block.transactions = chain.pending_transactions.drain(..take_count).collect();
+
+        // Authority and stake seals are a signature, cheap enough to make under the lock
+        if chain.properties.consensus != ConsensusEngine::ProofOfWork {
+            let add_res = <ConsensusEngine as Consensus>::prepare_block(chain, &mut block)
+                .and_then(|()| chain.add_block(block.clone()));
+            return settle(chain, block, add_res);
+        }
+
+        let target = ProofOfWork::prepare_template(chain, &mut block);
+        (block, target, chain.properties.hasher, chain.miner.clone(), chain.miner.ticket())
+    };
+
+    let mine_res = miner.mine(&mut block, target, hasher, ticket);
+
+    let mut node = lock_node(node);
+    let Some(chain) = node.as_mut() else {
+        return Ok(None);
+    };
+    let add_res = mine_res.and_then(|_report| chain.add_block(block.clone()));
+    settle(chain, block, add_res)
+}
+
+// Put transactions of a rejected block back in front of the pool.
+// A transaction that failed to execute is dropped so it can not block the rest.
+// Block built on a stale tip is kept as a fork, but its transactions are not
+// included until it becomes canonical so they are queued again
+fn settle(chain: &mut Chain, mut block: Block, add_res: Result<(), ConsensusError>) -> Result<Option<(Block, Hash)>, ConsensusError> {
+    let Err(add_err) = add_res else {
+        let hasher = chain.properties.hasher;
+        let block_hash = block.get_hash(hasher);
+        if chain.get_latest_block().get_hash(hasher) == block_hash {
+            return Ok(Some((block, block_hash)));
+        }
+        chain.pending_transactions.splice(0..0, block.transactions);
+        return Ok(None);
+    };
+
+    if let ConsensusError::Transaction { tx_index, .. } = &add_err {
+        block.transactions.remove(*tx_index);
+    }
+    chain.pending_transactions.splice(0..0, block.transactions);
+
+    Err(add_err)
+}
+
+/// Block interval matching the chain's own pace: its target block time
+/// under PoW, its slot under PoA and PoS
+pub fn default_block_interval(properties: &ChainProperties) -> Duration {
+    let interval = match properties.consensus {
+        ConsensusEngine::ProofOfWork => properties.target_block_time,
+        ConsensusEngine::ProofOfAuthority | ConsensusEngine::ProofOfStake => properties.slot_duration,
+    };
+    if interval.is_zero() {
+        return DEFAULT_BLOCK_INTERVAL;
+    }
+
+    interval
+}